@@ -6,11 +6,23 @@ logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
     let url = logoutLink.href;
+    // The auth service rejects cookie-authenticated POSTs without a CSRF token header
+    let csrfUrl = new URL('/csrf-token', url);
 
-    fetch(url, {
+    fetch(csrfUrl, {
+        credentials: 'include',
+    }).then(response => {
+        if (!response.ok) {
+            throw new Error("Failed to get CSRF token");
+        }
+        return response.json();
+    }).then(data => fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
-    }).then(response => {
+        headers: {
+            'X-CSRF-Token': data.csrfToken,
+        },
+    })).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
             logoutLink.style.display = "none";
@@ -18,7 +30,7 @@ logoutLink.addEventListener("click", (e) => {
         } else {
             alert("Failed to logout");
         }
-    });
+    }).catch(() => alert("Failed to logout"));
 });
 
 (() => {
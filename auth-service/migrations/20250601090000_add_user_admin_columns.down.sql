@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE users
+    DROP COLUMN IF EXISTS tokens_revoked_at,
+    DROP COLUMN IF EXISTS disabled,
+    DROP COLUMN IF EXISTS is_admin;
\ No newline at end of file
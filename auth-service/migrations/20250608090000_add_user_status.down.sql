@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users SET disabled = TRUE WHERE status = 'disabled';
+
+ALTER TABLE users DROP COLUMN IF EXISTS status;
\ No newline at end of file
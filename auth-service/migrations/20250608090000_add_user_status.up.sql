@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'disabled', 'pending-verification'));
+
+UPDATE users SET status = 'disabled' WHERE disabled;
+
+ALTER TABLE users DROP COLUMN IF EXISTS disabled;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_events
+(
+    id          BIGSERIAL   PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    kind        TEXT        NOT NULL,
+    email       TEXT,
+    ip          TEXT,
+    user_agent  TEXT,
+    request_id  TEXT,
+    detail      TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_events_email_occurred_at_idx ON audit_events (email, occurred_at DESC);
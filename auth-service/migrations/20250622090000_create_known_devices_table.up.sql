@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS known_devices
+(
+    email         TEXT        NOT NULL REFERENCES users (email) ON DELETE CASCADE,
+    ip            TEXT        NOT NULL,
+    user_agent    TEXT        NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (email, ip, user_agent)
+);
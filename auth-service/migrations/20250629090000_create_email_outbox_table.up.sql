@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS email_outbox
+(
+    id              BIGSERIAL   PRIMARY KEY,
+    idempotency_key TEXT        NOT NULL UNIQUE,
+    recipient       TEXT        NOT NULL,
+    subject         TEXT        NOT NULL,
+    html_body       TEXT        NOT NULL,
+    text_body       TEXT        NOT NULL,
+    status          TEXT        NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'dead')),
+    attempts        INTEGER     NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS email_outbox_due_idx ON email_outbox (next_attempt_at) WHERE status = 'pending';
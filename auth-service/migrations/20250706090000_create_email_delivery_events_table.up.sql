@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS email_delivery_events
+(
+    id          BIGSERIAL   PRIMARY KEY,
+    recipient   TEXT        NOT NULL,
+    kind        TEXT        NOT NULL CHECK (kind IN ('delivered', 'hard_bounce', 'soft_bounce', 'spam_complaint')),
+    occurred_at TIMESTAMPTZ NOT NULL,
+    message_id  TEXT,
+    detail      TEXT
+);
+
+CREATE INDEX IF NOT EXISTS email_delivery_events_recipient_idx ON email_delivery_events (recipient, occurred_at DESC);
@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE email_outbox
+    ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+-- Finished emails no longer need their bodies, which may hold a 2FA code
+UPDATE email_outbox SET html_body = '', text_body = '' WHERE status <> 'pending';
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DELETE FROM email_delivery_events WHERE kind = 'suppression_cleared';
+ALTER TABLE email_delivery_events DROP CONSTRAINT IF EXISTS email_delivery_events_kind_check;
+ALTER TABLE email_delivery_events
+    ADD CONSTRAINT email_delivery_events_kind_check
+        CHECK (kind IN ('delivered', 'hard_bounce', 'soft_bounce', 'spam_complaint'));
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE email_delivery_events DROP CONSTRAINT IF EXISTS email_delivery_events_kind_check;
+ALTER TABLE email_delivery_events
+    ADD CONSTRAINT email_delivery_events_kind_check
+        CHECK (kind IN ('delivered', 'hard_bounce', 'soft_bounce', 'spam_complaint', 'suppression_cleared'));
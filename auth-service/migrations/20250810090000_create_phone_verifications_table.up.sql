@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS phone_verifications
+(
+    email             TEXT        PRIMARY KEY,
+    phone_number      TEXT        NOT NULL,
+    login_attempt_id  TEXT        NOT NULL,
+    code              TEXT        NOT NULL,
+    expires_at        TIMESTAMPTZ NOT NULL,
+    -- Codes sent since window_started_at, for the send limit
+    window_started_at TIMESTAMPTZ NOT NULL,
+    sends             INTEGER     NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
+
+-- Tokens issued before this migration carry no version, so keep rejecting them for users
+-- whose tokens were revoked
+UPDATE users SET token_version = 1 WHERE tokens_revoked_at IS NOT NULL;
@@ -1,33 +1,84 @@
-use crate::EmailClient;
-use crate::domain::{BannedTokenStore, TwoFACodeStore, UserStore};
+use crate::domain::{
+    AuditSink, BannedTokenStore, DependencyCheck, EmailDeliveryStore, EmailOutbox, HealthCheckType,
+    KnownDeviceStore, PhoneVerificationStore, SmsClient, TwoFACodeStore, UserStore,
+};
+use crate::settings::Settings;
+use crate::{DevMailboxEmailClient, EmailClient};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 // Using a type alias to improve readability!
-pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
-pub type BannedStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
+pub type BannedStoreType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore + Send + Sync>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type EmailOutboxType = Arc<dyn EmailOutbox + Send + Sync>;
+pub type AuditSinkType = Arc<dyn AuditSink + Send + Sync>;
+pub type KnownDeviceStoreType = Arc<dyn KnownDeviceStore + Send + Sync>;
+pub type SmsClientType = Arc<dyn SmsClient + Send + Sync>;
+pub type EmailDeliveryStoreType = Arc<dyn EmailDeliveryStore + Send + Sync>;
+pub type PhoneVerificationStoreType = Arc<dyn PhoneVerificationStore + Send + Sync>;
 #[derive(Clone)]
 pub struct AppState {
+    pub settings: Arc<Settings>,
     pub user_store: UserStoreType,
     pub banned_token_store: BannedStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub audit_sink: AuditSinkType,
+    pub known_device_store: KnownDeviceStoreType,
+    pub email_outbox: EmailOutboxType,
+    pub email_delivery_store: EmailDeliveryStoreType,
+    pub sms_client: SmsClientType,
+    pub phone_verification_store: PhoneVerificationStoreType,
+    // Set only in local development, enables the `/dev/mailbox` routes
+    pub dev_mailbox: Option<Arc<DevMailboxEmailClient>>,
+    // Dependencies pinged by `GET /health/ready`
+    pub health_checks: Vec<DependencyCheck>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        settings: Arc<Settings>,
         user_store: UserStoreType,
         banned_token_store: BannedStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        audit_sink: AuditSinkType,
+        known_device_store: KnownDeviceStoreType,
+        email_outbox: EmailOutboxType,
+        email_delivery_store: EmailDeliveryStoreType,
+        sms_client: SmsClientType,
+        phone_verification_store: PhoneVerificationStoreType,
     ) -> Self {
         AppState {
+            settings,
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            audit_sink,
+            known_device_store,
+            email_outbox,
+            email_delivery_store,
+            sms_client,
+            phone_verification_store,
+            dev_mailbox: None,
+            health_checks: Vec::new(),
         }
     }
+
+    pub fn with_dev_mailbox(mut self, dev_mailbox: Option<Arc<DevMailboxEmailClient>>) -> Self {
+        self.dev_mailbox = dev_mailbox;
+        self
+    }
+
+    pub fn with_health_check(mut self, name: &str, critical: bool, check: HealthCheckType) -> Self {
+        self.health_checks.push(DependencyCheck {
+            name: name.to_owned(),
+            critical,
+            check,
+        });
+        self
+    }
 }
@@ -0,0 +1,174 @@
+use crate::domain::Email;
+use chrono::{DateTime, Utc};
+use color_eyre::Report;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// This trait represents the interface all concrete audit sinks should implement
+#[async_trait::async_trait]
+pub trait AuditSink {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditSinkError>;
+    async fn query(&self, query: &AuditQuery) -> Result<AuditPage, AuditSinkError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditSinkError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuditSinkError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Security-relevant events recorded in the audit log
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AuditEventKind {
+    #[serde(rename = "signup")]
+    Signup,
+    #[serde(rename = "login_succeeded")]
+    LoginSucceeded,
+    #[serde(rename = "login_failed")]
+    LoginFailed,
+    #[serde(rename = "two_fa_sent")]
+    TwoFASent,
+    #[serde(rename = "two_fa_verified")]
+    TwoFAVerified,
+    #[serde(rename = "two_fa_failed")]
+    TwoFAFailed,
+    #[serde(rename = "logout")]
+    Logout,
+    #[serde(rename = "token_rejected")]
+    TokenRejected,
+}
+
+impl AuditEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "signup" => Ok(Self::Signup),
+            "login_succeeded" => Ok(Self::LoginSucceeded),
+            "login_failed" => Ok(Self::LoginFailed),
+            "two_fa_sent" => Ok(Self::TwoFASent),
+            "two_fa_verified" => Ok(Self::TwoFAVerified),
+            "two_fa_failed" => Ok(Self::TwoFAFailed),
+            "logout" => Ok(Self::Logout),
+            "token_rejected" => Ok(Self::TokenRejected),
+            _ => Err(eyre!("{} is not a valid audit event kind", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::TwoFASent => "two_fa_sent",
+            Self::TwoFAVerified => "two_fa_verified",
+            Self::TwoFAFailed => "two_fa_failed",
+            Self::Logout => "logout",
+            Self::TokenRejected => "token_rejected",
+        }
+    }
+}
+
+// Where a request came from, captured once per request and copied into every audit event
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RequestMetadata {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: DateTime<Utc>,
+    pub kind: AuditEventKind,
+    pub email: Option<String>,
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, email: Option<&Email>, metadata: &RequestMetadata) -> Self {
+        Self {
+            occurred_at: Utc::now(),
+            kind,
+            email: email.map(|email| email.as_ref().expose_secret().to_owned()),
+            ip: metadata.ip.clone(),
+            user_agent: metadata.user_agent.clone(),
+            request_id: metadata.request_id.clone(),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+// Filters applied when querying the audit log. Results are newest first.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub email: Option<String>,
+    pub kind: Option<AuditEventKind>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.email
+            .as_ref()
+            .is_none_or(|email| event.email.as_ref() == Some(email))
+            && self.kind.is_none_or(|kind| event.kind == kind)
+            && self.from.is_none_or(|from| event.occurred_at >= from)
+            && self.to.is_none_or(|to| event.occurred_at < to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditPage {
+    pub events: Vec<AuditEvent>,
+    pub total: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_audit_event_kind() {
+        for kind in [
+            AuditEventKind::Signup,
+            AuditEventKind::LoginSucceeded,
+            AuditEventKind::LoginFailed,
+            AuditEventKind::TwoFASent,
+            AuditEventKind::TwoFAVerified,
+            AuditEventKind::TwoFAFailed,
+            AuditEventKind::Logout,
+            AuditEventKind::TokenRejected,
+        ] {
+            assert_eq!(AuditEventKind::parse(kind.as_str()).unwrap(), kind);
+            assert_eq!(
+                serde_json::to_string(&kind).unwrap(),
+                format!("\"{}\"", kind.as_str())
+            );
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::domain::Email;
-use crate::{Password, User};
+use crate::{AccountStatus, Password, PhoneNumber, TwoFAChannel, User};
 use color_eyre::eyre::{Context, eyre};
 use color_eyre::{Report, Result};
 use rand::Rng;
@@ -12,12 +12,12 @@ use uuid::Uuid;
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
         email: &Email,
@@ -47,7 +47,7 @@ pub struct LoginAttemptId(SecretString);
 impl LoginAttemptId {
     pub fn parse(id: SecretString) -> Result<Self> {
         // Use the `parse_str` function from the `uuid` crate to ensure `id` is a valid UUID
-        match Uuid::parse_str(&id.expose_secret()) {
+        match Uuid::parse_str(id.expose_secret()) {
             Ok(uuid) => Ok(Self(SecretString::from(uuid.to_string()))),
             Err(_) => Err(eyre!("{} is not a valid uuid", id.expose_secret())),
         }
@@ -118,7 +118,7 @@ mod test {
     #[test]
     fn should_parse_code_start_with_zero() {
         let result = TwoFACode::parse(SecretString::from("052321"));
-        assert_eq!(result.is_ok(), true);
+        assert!(result.is_ok());
     }
 }
 
@@ -148,8 +148,49 @@ impl PartialEq for UserStoreError {
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
     -> Result<(), UserStoreError>;
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    async fn set_status(&self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    async fn revoke_tokens(&self, email: &Email) -> Result<(), UserStoreError>;
+    // Replacing the number clears its verification and falls back to email for 2FA
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_phone_verified(&self, email: &Email) -> Result<(), UserStoreError>;
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError>;
+}
+
+// Filter and pagination window used when listing users.
+// `search` is matched case-insensitively against any part of the email.
+#[derive(Debug, Clone, Default)]
+pub struct UserQuery {
+    pub search: Option<String>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+// One page of users ordered by email, with the total number of matching users.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub total: u64,
 }
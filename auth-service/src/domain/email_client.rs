@@ -1,7 +1,44 @@
 use super::Email;
-use color_eyre::Result;
+use color_eyre::{Report, Result};
+use thiserror::Error;
 // This trait represents the interface all concrete email clients should implement
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+
+    // Checks the provider can be reached without sending anything, clients with nothing to
+    // reach are always healthy
+    async fn check_health(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// A rendered email with an HTML body and a plain-text fallback
+#[derive(Clone, PartialEq, Debug)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+// Email clients wrap their errors in this type so callers can decide whether trying again,
+// possibly with another provider, has any chance of succeeding.
+#[derive(Debug, Error)]
+pub enum EmailDeliveryError {
+    // Provider outage, timeout or rate limit
+    #[error("Retryable email delivery error")]
+    Retryable(#[source] Report),
+    // The email itself was rejected, e.g. an invalid recipient
+    #[error("Permanent email delivery error")]
+    Permanent(#[source] Report),
+}
+
+impl EmailDeliveryError {
+    // Errors that were not classified by the email client are treated as retryable
+    pub fn is_permanent(error: &Report) -> bool {
+        matches!(
+            error.downcast_ref::<EmailDeliveryError>(),
+            Some(EmailDeliveryError::Permanent(_))
+        )
+    }
 }
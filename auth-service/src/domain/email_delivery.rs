@@ -0,0 +1,113 @@
+use crate::domain::Email;
+use chrono::{DateTime, Utc};
+use color_eyre::Report;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use thiserror::Error;
+
+// This trait represents the interface all concrete email delivery stores should implement.
+// It keeps the delivery history reported by the email provider for every recipient.
+#[async_trait::async_trait]
+pub trait EmailDeliveryStore {
+    async fn record_event(&self, event: DeliveryEvent) -> Result<(), EmailDeliveryStoreError>;
+    // True when the latest definitive event for the recipient is a hard bounce or spam complaint.
+    // Recording a `SuppressionCleared` event lifts this until the provider reports another one.
+    async fn is_undeliverable(&self, recipient: &Email) -> Result<bool, EmailDeliveryStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailDeliveryStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailDeliveryStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeliveryEventKind {
+    Delivered,
+    HardBounce,
+    SoftBounce,
+    SpamComplaint,
+    // An admin confirmed the address works again, e.g. after the user fixed their mailbox
+    SuppressionCleared,
+}
+
+impl DeliveryEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "delivered" => Ok(Self::Delivered),
+            "hard_bounce" => Ok(Self::HardBounce),
+            "soft_bounce" => Ok(Self::SoftBounce),
+            "spam_complaint" => Ok(Self::SpamComplaint),
+            "suppression_cleared" => Ok(Self::SuppressionCleared),
+            _ => Err(eyre!("{} is not a valid delivery event kind", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delivered => "delivered",
+            Self::HardBounce => "hard_bounce",
+            Self::SoftBounce => "soft_bounce",
+            Self::SpamComplaint => "spam_complaint",
+            Self::SuppressionCleared => "suppression_cleared",
+        }
+    }
+
+    // Soft bounces are temporary and say nothing about whether the address works
+    pub fn is_definitive(&self) -> bool {
+        !matches!(self, Self::SoftBounce)
+    }
+
+    pub fn is_undeliverable(&self) -> bool {
+        matches!(self, Self::HardBounce | Self::SpamComplaint)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DeliveryEvent {
+    // Lowercased so lookups don't depend on how the provider spells the address
+    pub recipient: String,
+    pub kind: DeliveryEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub message_id: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl DeliveryEvent {
+    pub fn new(recipient: &str, kind: DeliveryEventKind, occurred_at: DateTime<Utc>) -> Self {
+        Self {
+            recipient: recipient.to_lowercase(),
+            kind,
+            occurred_at,
+            message_id: None,
+            detail: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_delivery_event_kind() {
+        for kind in [
+            DeliveryEventKind::Delivered,
+            DeliveryEventKind::HardBounce,
+            DeliveryEventKind::SoftBounce,
+            DeliveryEventKind::SpamComplaint,
+            DeliveryEventKind::SuppressionCleared,
+        ] {
+            assert_eq!(DeliveryEventKind::parse(kind.as_str()).unwrap(), kind);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::domain::{Email, EmailMessage};
+use chrono::{DateTime, Utc};
+use color_eyre::Report;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::time::Duration;
+use thiserror::Error;
+
+// This trait represents the interface all concrete email outboxes should implement.
+// Emails are delivered at least once by `EmailOutboxWorker`. Delivered and dead emails
+// keep their subject but lose their bodies, which may hold a 2FA code.
+#[async_trait::async_trait]
+pub trait EmailOutbox {
+    // Returns false if an email with the same idempotency key was already queued
+    async fn enqueue(&self, email: OutboxEmail) -> Result<bool, EmailOutboxError>;
+    // Hands out up to `limit` due emails and hides them from other workers for `lease`
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxError>;
+    async fn mark_delivered(&self, id: i64) -> Result<(), EmailOutboxError>;
+    // Schedules another attempt at `retry_at`, or dead-letters the email when it is `None`
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError>;
+    async fn get_entry(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxEntry>, EmailOutboxError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxError {
+    #[error("Outbox entry not found")]
+    EntryNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailOutboxError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EntryNotFound, Self::EntryNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// An email waiting to be queued. The idempotency key makes enqueueing the same email twice harmless.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutboxEmail {
+    pub idempotency_key: String,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    // Dead-lettered instead of sent after this, for emails like 2FA codes that go stale
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub email: OutboxEmail,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutboxStatus {
+    Pending,
+    Delivered,
+    // Gave up after too many failed attempts
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "dead" => Ok(Self::Dead),
+            _ => Err(eyre!("{} is not a valid outbox status", status)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+// Exponential backoff between delivery attempts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    // Delay before the next attempt, or `None` once `attempts` have been used up
+    pub fn next_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        Some(self.base_delay.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_double_delay_until_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+        };
+
+        assert_eq!(policy.next_delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(policy.next_delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(policy.next_delay(3), Some(Duration::from_secs(4)));
+        assert_eq!(policy.next_delay(4), Some(Duration::from_secs(5)));
+        assert_eq!(policy.next_delay(10), None);
+    }
+
+    #[test]
+    fn should_round_trip_outbox_status() {
+        for status in [
+            OutboxStatus::Pending,
+            OutboxStatus::Delivered,
+            OutboxStatus::Dead,
+        ] {
+            assert_eq!(OutboxStatus::parse(status.as_str()).unwrap(), status);
+        }
+    }
+}
@@ -13,6 +13,22 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Invalid CSRF token")]
+    InvalidCsrfToken,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("Account pending verification")]
+    AccountPendingVerification,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("Email undeliverable")]
+    EmailUndeliverable,
+    #[error("Too many verification codes sent")]
+    TooManyVerificationCodes,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
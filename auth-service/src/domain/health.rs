@@ -0,0 +1,19 @@
+use color_eyre::Result;
+use std::sync::Arc;
+
+// A dependency the service needs to handle requests, pinged by `GET /health/ready`
+#[async_trait::async_trait]
+pub trait HealthCheck {
+    async fn check(&self) -> Result<()>;
+}
+
+pub type HealthCheckType = Arc<dyn HealthCheck + Send + Sync>;
+
+// A named health check. The service is reported unavailable when a critical one fails, while
+// failures of the others only show up in the report.
+#[derive(Clone)]
+pub struct DependencyCheck {
+    pub name: String,
+    pub critical: bool,
+    pub check: HealthCheckType,
+}
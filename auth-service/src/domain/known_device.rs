@@ -0,0 +1,56 @@
+use crate::domain::{Email, RequestMetadata};
+use color_eyre::Report;
+use thiserror::Error;
+
+// This trait represents the interface all concrete known device stores should implement
+#[async_trait::async_trait]
+pub trait KnownDeviceStore {
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: Device,
+    ) -> Result<DeviceSighting, KnownDeviceStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum KnownDeviceStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for KnownDeviceStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// A device is identified by the client IP and user agent a user signed in from.
+// Unknown values are stored as empty strings so they still compare equal.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Device {
+    pub ip: String,
+    pub user_agent: String,
+}
+
+impl From<&RequestMetadata> for Device {
+    fn from(metadata: &RequestMetadata) -> Self {
+        Self {
+            ip: metadata.ip.clone().unwrap_or_default(),
+            user_agent: metadata.user_agent.clone().unwrap_or_default(),
+        }
+    }
+}
+
+// Outcome of remembering a device
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeviceSighting {
+    // The user has signed in from this device before
+    Known,
+    // The device is new, but the user has signed in from other devices before
+    New,
+    // The first device ever seen for the user, e.g. right after signup
+    First,
+}
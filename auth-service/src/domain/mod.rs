@@ -1,15 +1,33 @@
+mod audit;
 mod data_stores;
 mod email;
 mod email_client;
+mod email_delivery;
+mod email_outbox;
 mod error;
+mod health;
+mod known_device;
 mod password;
+mod phone_number;
+mod phone_verification;
+mod security_notification;
+mod sms_client;
 mod token_store;
 mod user;
 
+pub use audit::*;
 pub use data_stores::*;
 pub use email::*;
 pub use email_client::*;
+pub use email_delivery::*;
+pub use email_outbox::*;
 pub use error::*;
+pub use health::*;
+pub use known_device::*;
 pub use password::*;
+pub use phone_number::*;
+pub use phone_verification::*;
+pub use security_notification::*;
+pub use sms_client::*;
 pub use token_store::*;
 pub use user::*;
@@ -0,0 +1,60 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+// A phone number in E.164 format, e.g. `+14155552671`.
+// Spaces, dashes and parentheses are stripped before validation.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PhoneNumber(SecretString);
+
+impl PhoneNumber {
+    pub fn parse(phone_number: SecretString) -> Result<Self> {
+        let normalized: String = phone_number
+            .expose_secret()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '(' | ')'))
+            .collect();
+        let digits = normalized
+            .strip_prefix('+')
+            .ok_or_else(|| eyre!("Phone number must start with a country code, e.g. +1"))?;
+        let valid = (8..=15).contains(&digits.len())
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && !digits.starts_with('0');
+        if !valid {
+            return Err(eyre!("Phone number is not a valid E.164 number"));
+        }
+        Ok(Self(SecretString::from(normalized)))
+    }
+}
+
+impl Eq for PhoneNumber {}
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<SecretString> for PhoneNumber {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_normalize_valid_phone_numbers() {
+        let phone_number = PhoneNumber::parse(SecretString::from("+1 (415) 555-2671")).unwrap();
+        assert_eq!(phone_number.as_ref().expose_secret(), "+14155552671");
+    }
+
+    #[test]
+    fn should_reject_invalid_phone_numbers() {
+        for phone_number in ["4155552671", "+0123456789", "+1415abc2671", "+1234", ""] {
+            assert!(PhoneNumber::parse(SecretString::from(phone_number)).is_err());
+        }
+    }
+}
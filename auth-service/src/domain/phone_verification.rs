@@ -0,0 +1,51 @@
+use crate::domain::{Email, LoginAttemptId, PhoneNumber, TwoFACode};
+use color_eyre::Report;
+use thiserror::Error;
+
+// This trait represents the interface all concrete phone verification stores should implement.
+// Codes proving a phone number are kept apart from login 2FA codes, so neither can be used
+// as the other and starting one never replaces the other.
+#[async_trait::async_trait]
+pub trait PhoneVerificationStore {
+    // Replaces the user's pending verification. Fails with `TooManyCodesSent` instead once
+    // the user has been sent as many codes as the send limit allows.
+    async fn add_code(
+        &self,
+        email: Email,
+        verification: PhoneVerification,
+    ) -> Result<(), PhoneVerificationStoreError>;
+    async fn remove_code(&self, email: &Email) -> Result<(), PhoneVerificationStoreError>;
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<PhoneVerification, PhoneVerificationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PhoneVerificationStoreError {
+    #[error("Phone verification not found")]
+    VerificationNotFound,
+    #[error("Too many verification codes sent")]
+    TooManyCodesSent,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PhoneVerificationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::VerificationNotFound, Self::VerificationNotFound)
+                | (Self::TooManyCodesSent, Self::TooManyCodesSent)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// A code texted to `phone_number`, which only verifies that number
+#[derive(Clone, PartialEq, Debug)]
+pub struct PhoneVerification {
+    pub phone_number: PhoneNumber,
+    pub login_attempt_id: LoginAttemptId,
+    pub code: TwoFACode,
+}
@@ -0,0 +1,19 @@
+use crate::domain::Device;
+
+// Emails sent to a user when something sensitive happens to their account
+#[derive(Clone, PartialEq, Debug)]
+pub enum SecurityNotification {
+    NewDevice(Device),
+    PasswordChanged,
+    TwoFADisabled,
+}
+
+impl SecurityNotification {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NewDevice(_) => "new-device",
+            Self::PasswordChanged => "password-changed",
+            Self::TwoFADisabled => "two-fa-disabled",
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use super::PhoneNumber;
+use color_eyre::Result;
+
+// This trait represents the interface all concrete SMS clients should implement
+#[async_trait::async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()>;
+}
@@ -20,6 +20,6 @@ impl PartialEq for BannedTokenStoreError {
 }
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError>;
+    async fn add_token(&self, token: &SecretString) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError>;
 }
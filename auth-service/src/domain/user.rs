@@ -1,4 +1,8 @@
-use crate::domain::{Email, Password};
+use crate::domain::{Email, Password, PhoneNumber};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
 
 // The User struct should contain 3 fields. email, which is a String;
 // password, which is also a String; and requires_2fa, which is a boolean.
@@ -7,6 +11,17 @@ pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub is_admin: bool,
+    pub status: AccountStatus,
+    // When the user's tokens were last revoked, for admins to see
+    pub tokens_revoked_at: Option<DateTime<Utc>>,
+    // Copied into every token issued to the user. Revoking bumps it, so `validate_token`
+    // rejects every token issued before.
+    pub token_version: u32,
+    pub phone_number: Option<PhoneNumber>,
+    // Set once the user proved they receive texts at `phone_number`
+    pub phone_verified: bool,
+    pub two_fa_channel: TwoFAChannel,
 }
 
 impl User {
@@ -15,6 +30,117 @@ impl User {
             email,
             password,
             requires_2fa,
+            is_admin: false,
+            status: AccountStatus::Active,
+            tokens_revoked_at: None,
+            token_version: 0,
+            phone_number: None,
+            phone_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
         }
     }
+
+    // The number 2FA codes should be texted to, if the user prefers SMS and has verified one
+    pub fn sms_phone_number(&self) -> Option<&PhoneNumber> {
+        match self.two_fa_channel {
+            TwoFAChannel::Sms if self.phone_verified => self.phone_number.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+// Lifecycle state of an account, stored as text in the `status` column.
+// Disabled and pending-verification accounts can neither sign in nor use any
+// previously issued token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Disabled,
+    PendingVerification,
+}
+
+impl AccountStatus {
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "active" => Ok(Self::Active),
+            "disabled" => Ok(Self::Disabled),
+            "pending-verification" => Ok(Self::PendingVerification),
+            _ => Err(eyre!("{} is not a valid account status", status)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Disabled => "disabled",
+            Self::PendingVerification => "pending-verification",
+        }
+    }
+}
+
+// Where a user prefers to receive 2FA codes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn parse(channel: &str) -> Result<Self> {
+        match channel {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            _ => Err(eyre!("{} is not a valid 2FA channel", channel)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    #[test]
+    fn should_round_trip_account_status() {
+        for status in [
+            AccountStatus::Active,
+            AccountStatus::Disabled,
+            AccountStatus::PendingVerification,
+        ] {
+            assert_eq!(AccountStatus::parse(status.as_str()).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn should_only_text_verified_phone_numbers() {
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let password = Password::parse(SecretString::from("password")).unwrap();
+        let mut user = User::new(email, password, true);
+        user.phone_number = Some(PhoneNumber::parse(SecretString::from("+14155552671")).unwrap());
+        user.two_fa_channel = TwoFAChannel::Sms;
+        assert_eq!(user.sms_phone_number(), None);
+
+        user.phone_verified = true;
+        assert_eq!(user.sms_phone_number(), user.phone_number.as_ref());
+        assert_eq!(
+            TwoFAChannel::parse(TwoFAChannel::Sms.as_str()).unwrap(),
+            TwoFAChannel::Sms
+        );
+    }
+
+    #[test]
+    fn should_reject_unknown_account_status() {
+        assert!(AccountStatus::parse("suspended").is_err());
+    }
 }
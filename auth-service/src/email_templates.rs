@@ -0,0 +1,149 @@
+use crate::domain::{EmailMessage, SecurityNotification};
+use askama::Template;
+use color_eyre::Result;
+
+// Language an email is rendered in. Only English ships today; a new locale needs its own
+// templates under `templates/email/<locale>` and a match arm in each function below.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Locale {
+    #[default]
+    En,
+}
+
+pub fn two_fa_code_email(code: &str, locale: Locale) -> Result<EmailMessage> {
+    match locale {
+        Locale::En => Ok(EmailMessage {
+            subject: "Your verification code".to_owned(),
+            html_body: en::TwoFACodeHtml { code }.render()?,
+            text_body: en::TwoFACodeText { code }.render()?,
+        }),
+    }
+}
+
+// Text messages are short enough to not need a template, but share the locale handling
+pub fn two_fa_code_sms(code: &str, locale: Locale) -> String {
+    match locale {
+        Locale::En => format!("Your verification code is {code}. It can only be used once."),
+    }
+}
+
+pub fn security_notification_email(
+    notification: &SecurityNotification,
+    locale: Locale,
+) -> Result<EmailMessage> {
+    match (locale, notification) {
+        (Locale::En, SecurityNotification::NewDevice(device)) => {
+            let ip = or_unknown(&device.ip);
+            let user_agent = or_unknown(&device.user_agent);
+            Ok(EmailMessage {
+                subject: "New sign-in to your account".to_owned(),
+                html_body: en::NewDeviceHtml { ip, user_agent }.render()?,
+                text_body: en::NewDeviceText { ip, user_agent }.render()?,
+            })
+        }
+        (Locale::En, SecurityNotification::PasswordChanged) => Ok(EmailMessage {
+            subject: "Your password was changed".to_owned(),
+            html_body: en::PasswordChangedHtml.render()?,
+            text_body: en::PasswordChangedText.render()?,
+        }),
+        (Locale::En, SecurityNotification::TwoFADisabled) => Ok(EmailMessage {
+            subject: "Two-factor authentication was disabled".to_owned(),
+            html_body: en::TwoFADisabledHtml.render()?,
+            text_body: en::TwoFADisabledText.render()?,
+        }),
+    }
+}
+
+fn or_unknown(value: &str) -> &str {
+    if value.is_empty() { "unknown" } else { value }
+}
+
+// Templates are compiled in, `.html` variants are HTML-escaped and `.txt` variants are not
+mod en {
+    use askama::Template;
+
+    #[derive(Template)]
+    #[template(path = "email/en/two_fa_code.html")]
+    pub struct TwoFACodeHtml<'a> {
+        pub code: &'a str,
+    }
+
+    #[derive(Template)]
+    #[template(path = "email/en/two_fa_code.txt")]
+    pub struct TwoFACodeText<'a> {
+        pub code: &'a str,
+    }
+
+    #[derive(Template)]
+    #[template(path = "email/en/new_device.html")]
+    pub struct NewDeviceHtml<'a> {
+        pub ip: &'a str,
+        pub user_agent: &'a str,
+    }
+
+    #[derive(Template)]
+    #[template(path = "email/en/new_device.txt")]
+    pub struct NewDeviceText<'a> {
+        pub ip: &'a str,
+        pub user_agent: &'a str,
+    }
+
+    #[derive(Template)]
+    #[template(path = "email/en/password_changed.html")]
+    pub struct PasswordChangedHtml;
+
+    #[derive(Template)]
+    #[template(path = "email/en/password_changed.txt")]
+    pub struct PasswordChangedText;
+
+    #[derive(Template)]
+    #[template(path = "email/en/two_fa_disabled.html")]
+    pub struct TwoFADisabledHtml;
+
+    #[derive(Template)]
+    #[template(path = "email/en/two_fa_disabled.txt")]
+    pub struct TwoFADisabledText;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Device;
+
+    #[test]
+    fn should_render_two_fa_code_in_both_variants() {
+        let message = two_fa_code_email("123456", Locale::En).unwrap();
+
+        assert_eq!(message.subject, "Your verification code");
+        assert!(message.html_body.contains("<html"));
+        assert!(message.html_body.contains("123456"));
+        assert!(!message.text_body.contains('<'));
+        assert!(message.text_body.contains("123456"));
+    }
+
+    #[test]
+    fn should_escape_device_details_in_html_only() {
+        let notification = SecurityNotification::NewDevice(Device {
+            ip: "203.0.113.7".to_owned(),
+            user_agent: "<script>".to_owned(),
+        });
+
+        let message = security_notification_email(&notification, Locale::En).unwrap();
+
+        assert!(message.html_body.contains("&lt;script&gt;"));
+        assert!(message.text_body.contains("User agent: <script>"));
+        assert!(message.text_body.contains("IP address: 203.0.113.7"));
+    }
+
+    #[test]
+    fn should_render_unknown_device_details() {
+        let notification = SecurityNotification::NewDevice(Device {
+            ip: String::new(),
+            user_agent: String::new(),
+        });
+
+        let message = security_notification_email(&notification, Locale::En).unwrap();
+
+        assert!(message.text_body.contains("User agent: unknown"));
+    }
+}
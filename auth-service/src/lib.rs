@@ -1,8 +1,15 @@
-use crate::routes::{login, logout, signup, verify_2fa, verify_token};
-use axum::http::{Method, StatusCode};
+use crate::routes::{
+    clear_email_suppression, disable_user, enable_user, get_csrf_token, get_metrics, get_user,
+    list_audit_events, list_dev_mailbox, list_users, liveness, login, logout, postmark_webhook,
+    readiness, require_admin, require_csrf_token, reset_password, revoke_tokens, set_phone_number,
+    set_requires_2fa, set_two_fa_channel, show_dev_mailbox_email, signup, verify_2fa,
+    verify_phone_number, verify_token,
+};
+use axum::http::StatusCode;
+use axum::middleware;
 use axum::response::{IntoResponse, Response};
-use axum::routing::post;
-use axum::serve::Serve;
+use axum::routing::{get, post};
+use axum::serve::ListenerExt;
 use axum::{Json, Router};
 use redis::{Client, RedisResult};
 use secrecy::{ExposeSecret, SecretString};
@@ -10,26 +17,43 @@ use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::error::Error;
+use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tower_http::cors::CorsLayer;
+use tokio_util::sync::CancellationToken;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 
 mod app_state;
 pub mod domain;
+pub mod email_templates;
 pub use domain::*;
 pub mod routes;
 mod services;
+pub mod settings;
 pub mod utils;
-use crate::utils::{make_span_with_request_id, on_request, on_response};
+use crate::utils::{
+    TlsListener, cors_layer, https_redirect_router, make_span_with_request_id, on_request,
+    on_response, set_request_id, set_route_labels,
+};
 pub use app_state::*;
 pub use services::*;
 
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    router: Router,
+    listener: AppListener,
+    // Plain HTTP listener redirecting to HTTPS
+    http_redirect: Option<(TcpListener, Router)>,
+    shutdown_timeout: Duration,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
+    pub http_redirect_address: Option<String>,
+}
+
+enum AppListener {
+    Plain(TcpListener),
+    Tls(TlsListener),
 }
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
@@ -37,43 +61,146 @@ pub struct ErrorResponse {
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "142.93.34.195:8000".parse()?,
-        ];
-        let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
+    pub async fn build(app_state: AppState) -> Result<Self, Box<dyn Error>> {
+        let settings = app_state.settings.clone();
+        let cors = cors_layer(&settings.cors)?;
 
         let trace_layer = TraceLayer::new_for_http()
             .make_span_with(make_span_with_request_id)
             .on_request(on_request)
             .on_response(on_response);
 
-        let router = Router::new()
+        let admin_router = Router::new()
+            .route("/users", get(list_users))
+            .route("/users/{email}", get(get_user))
+            .route("/users/{email}/disable", post(disable_user))
+            .route("/users/{email}/enable", post(enable_user))
+            .route("/users/{email}/reset-password", post(reset_password))
+            .route("/users/{email}/requires-2fa", post(set_requires_2fa))
+            .route("/users/{email}/revoke-tokens", post(revoke_tokens))
+            .route(
+                "/users/{email}/clear-email-suppression",
+                post(clear_email_suppression),
+            )
+            .route("/audit-events", get(list_audit_events))
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_admin,
+            ));
+
+        // Routes a browser authenticates with the auth cookie, guarded against CSRF
+        let cookie_authenticated_router = Router::new()
+            .route("/logout", post(logout))
+            .route("/phone-number", post(set_phone_number))
+            .route("/phone-number/verify", post(verify_phone_number))
+            .route("/two-fa-channel", post(set_two_fa_channel))
+            .nest("/admin", admin_router)
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_csrf_token,
+            ));
+
+        let mut router = Router::new()
             .fallback_service(ServeDir::new("assets"))
             .route("/signup", post(signup))
             .route("/login", post(login))
-            .route("/logout", post(logout))
             .route("/verify-token", post(verify_token))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/csrf-token", get(get_csrf_token))
+            .route("/health/live", get(liveness))
+            .route("/health/ready", get(readiness))
+            .route("/metrics", get(get_metrics))
+            .route("/webhooks/postmark", post(postmark_webhook))
+            .merge(cookie_authenticated_router);
+        if app_state.dev_mailbox.is_some() {
+            tracing::warn!("dev mailbox enabled, emails are captured instead of delivered");
+            router = router
+                .route("/dev/mailbox", get(list_dev_mailbox))
+                .route("/dev/mailbox/{id}", get(show_dev_mailbox_email));
+        }
+        let router = router
             .with_state(app_state)
             .layer(cors)
-            .layer(trace_layer);
+            .layer(middleware::from_fn(set_route_labels))
+            .layer(trace_layer)
+            .layer(middleware::from_fn(set_request_id));
 
-        let listener = TcpListener::bind(address).await?;
+        let listener = TcpListener::bind(settings.application.address()).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let listener = match settings.tls.enabled {
+            true => AppListener::Tls(TlsListener::new(listener, &settings.tls)?),
+            false => AppListener::Plain(listener),
+        };
+        let http_redirect = match settings.tls.redirect_http_port {
+            Some(port) if settings.tls.enabled => {
+                let address = format!("{}:{}", settings.application.host, port);
+                let router = https_redirect_router(&settings.application.public_url);
+                Some((TcpListener::bind(address).await?, router))
+            }
+            _ => None,
+        };
+        let http_redirect_address = match &http_redirect {
+            Some((listener, _)) => Some(listener.local_addr()?.to_string()),
+            None => None,
+        };
 
-        let app = Application { server, address };
+        let app = Application {
+            router,
+            listener,
+            http_redirect,
+            shutdown_timeout: settings.application.shutdown_timeout,
+            address,
+            http_redirect_address,
+        };
         Ok(app)
     }
 
-    pub async fn run(self) -> Result<(), std::io::Error> {
-        tracing::info!("listening on {}", &self.address);
-        self.server.await
+    // Serves until `shutdown` is cancelled, then stops accepting connections and gives in-flight
+    // requests up to `application.shutdown_timeout` to finish before returning
+    pub async fn run(self, shutdown: CancellationToken) -> Result<(), std::io::Error> {
+        if let Some((listener, router)) = self.http_redirect {
+            tracing::info!("redirecting http://{} to HTTPS", listener.local_addr()?);
+            tokio::spawn(
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+                    .into_future(),
+            );
+        }
+        // Connection info gives handlers access to the client IP for audit events
+        let service = self
+            .router
+            .into_make_service_with_connect_info::<SocketAddr>();
+        let signal = shutdown.clone().cancelled_owned();
+        let server = async {
+            match self.listener {
+                AppListener::Plain(listener) => {
+                    tracing::info!("listening on http://{}", &self.address);
+                    axum::serve(listener, service)
+                        .with_graceful_shutdown(signal)
+                        .await
+                }
+                AppListener::Tls(listener) => {
+                    tracing::info!("listening on https://{}", &self.address);
+                    axum::serve(listener.tap_io(|_| {}), service)
+                        .with_graceful_shutdown(signal)
+                        .await
+                }
+            }
+        };
+        let drain_deadline = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(self.shutdown_timeout).await;
+        };
+        tokio::select! {
+            result = server => result,
+            _ = drain_deadline => {
+                tracing::warn!(
+                    timeout = ?self.shutdown_timeout,
+                    "in-flight requests did not finish before the shutdown timeout"
+                );
+                Ok(())
+            }
+        }
     }
 }
 
@@ -88,6 +215,24 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::InvalidCsrfToken => (StatusCode::FORBIDDEN, "Invalid CSRF token"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::AccountPendingVerification => {
+                (StatusCode::FORBIDDEN, "Account pending verification")
+            }
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
+            AuthAPIError::EmailUndeliverable => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Cannot deliver 2FA code to this email address",
+            ),
+            AuthAPIError::TooManyVerificationCodes => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many verification codes sent, try again later",
+            ),
             AuthAPIError::UnexpectedError(_) => {
                 // Updated!
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
@@ -114,15 +259,17 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
     tracing::error!("{}", report);
 }
 
-pub async fn get_postgres_pool(url: &SecretString) -> Result<PgPool, sqlx::Error> {
+pub async fn get_postgres_pool(
+    url: &SecretString,
+    max_connections: u32,
+) -> Result<PgPool, sqlx::Error> {
     // Create a new PostgreSQL connection pool
     PgPoolOptions::new()
-        .max_connections(5)
+        .max_connections(max_connections)
         .connect(url.expose_secret())
         .await
 }
 
-pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
+pub fn get_redis_client(url: &SecretString) -> RedisResult<Client> {
+    redis::Client::open(url.expose_secret())
 }
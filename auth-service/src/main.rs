@@ -1,39 +1,130 @@
-use auth_service::utils::prod::APP_ADDRESS;
-use auth_service::utils::{DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, init_tracing, prod};
+use auth_service::settings::{EmailSettings, Settings, SmsSettings};
+use auth_service::utils::{init_tracing, shutdown_signal};
 use auth_service::{
-    AppState, Application, Email, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore,
-    RedisTwoFACodeStore, get_postgres_pool, get_redis_client,
+    AppState, Application, BannedStoreType, DevMailboxEmailClient, EmailClientType,
+    EmailOutboxWorker, EmailProviderHealthCheck, FailoverEmailClient, MockSmsClient,
+    PostgresAuditSink, PostgresBannedTokenStore, PostgresEmailDeliveryStore, PostgresEmailOutbox,
+    PostgresHealthCheck, PostgresKnownDeviceStore, PostgresPhoneVerificationStore,
+    PostgresTwoFACodeStore, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore,
+    RedisConnection, RedisHealthCheck, RedisTwoFACodeStore, SmsClientType, SmtpCredentials,
+    SmtpEmailClient, SmtpSettings, SmtpTls, TokenStorePurger, TwilioSmsClient, TwoFACodeStoreType,
+    get_postgres_pool,
 };
 use reqwest::Client;
-use secrecy::SecretString;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing().expect("Failed to init tracing");
-    let pg_pool = configure_postqresql().await;
-    let redis_conn = Arc::new(RwLock::new(configure_redis()));
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
-    let email_client = Arc::new(configure_postmark_email_client());
+    let settings = match Settings::load() {
+        Ok(settings) => Arc::new(settings),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let tracing_guard = init_tracing(&settings.telemetry).expect("Failed to init tracing");
+    let pg_pool = configure_postqresql(&settings).await;
+    // Redis is only needed when it holds the banned tokens and 2FA codes
+    let redis_conn = match settings.token_store.backend.as_str() {
+        "redis" => Some(configure_redis(&settings).await),
+        _ => None,
+    };
+    let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+    let audit_sink = Arc::new(PostgresAuditSink::new(pg_pool.clone()));
+    let known_device_store = Arc::new(PostgresKnownDeviceStore::new(pg_pool.clone()));
+    let email_delivery_store = Arc::new(PostgresEmailDeliveryStore::new(pg_pool.clone()));
+    let email_outbox = Arc::new(PostgresEmailOutbox::new(pg_pool.clone()));
+    let phone_verification_store = Arc::new(PostgresPhoneVerificationStore::new(
+        pg_pool.clone(),
+        settings.auth.two_fa_code_ttl,
+        settings.sms.verification_max_sends,
+        settings.sms.verification_send_window,
+    ));
+    let (banned_token_store, two_fa_code_store, token_store_purger) =
+        configure_token_stores(&settings, &pg_pool, redis_conn.as_ref());
+    let dev_mailbox = configure_dev_mailbox(&settings.email);
+    let email_client: EmailClientType = match &dev_mailbox {
+        Some(dev_mailbox) => dev_mailbox.clone(),
+        None => configure_email_client(&settings.email),
+    };
     let app_state = AppState::new(
+        settings.clone(),
         user_store.clone(),
         banned_token_store.clone(),
         two_fa_code_store.clone(),
         email_client.clone(),
+        audit_sink.clone(),
+        known_device_store.clone(),
+        email_outbox.clone(),
+        email_delivery_store,
+        configure_sms_client(&settings.sms),
+        phone_verification_store,
+    )
+    .with_dev_mailbox(dev_mailbox)
+    .with_health_check(
+        "postgres",
+        true,
+        Arc::new(PostgresHealthCheck::new(pg_pool.clone())),
+    );
+    let app_state = match &redis_conn {
+        Some(redis_conn) => app_state.with_health_check(
+            "redis",
+            true,
+            Arc::new(RedisHealthCheck::new(redis_conn.clone())),
+        ),
+        None => app_state,
+    };
+    let app_state = match settings.health.check_email_provider {
+        true => app_state.with_health_check(
+            "email",
+            false,
+            Arc::new(EmailProviderHealthCheck::new(email_client.clone())),
+        ),
+        false => app_state,
+    };
+    let email_outbox_worker = EmailOutboxWorker::new(
+        email_outbox,
+        email_client,
+        settings.email_outbox.retry_policy(),
+        settings.email_outbox.poll_interval,
     );
-    let app = Application::build(app_state, APP_ADDRESS)
+    let shutdown = CancellationToken::new();
+    let email_outbox_worker = tokio::spawn(email_outbox_worker.run(shutdown.clone()));
+    let token_store_purger =
+        token_store_purger.map(|purger| tokio::spawn(purger.run(shutdown.clone())));
+    let app = Application::build(app_state)
         .await
         .expect("Failed to build app");
-    app.run().await.expect("Failed to run app");
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            shutdown.cancel();
+        }
+    });
+    app.run(shutdown.clone()).await.expect("Failed to run app");
+
+    // Stop the workers before closing the connections they use
+    shutdown.cancel();
+    if let Err(e) = email_outbox_worker.await {
+        tracing::error!(error = ?e, "email outbox worker panicked");
+    }
+    if let Some(token_store_purger) = token_store_purger
+        && let Err(e) = token_store_purger.await
+    {
+        tracing::error!(error = ?e, "token store purger panicked");
+    }
+    pg_pool.close().await;
+    drop(redis_conn);
+    tracing::info!("shutdown complete");
+    tracing_guard.shutdown();
 }
 
-async fn configure_postqresql() -> PgPool {
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+async fn configure_postqresql(settings: &Settings) -> PgPool {
+    let pg_pool = get_postgres_pool(&settings.database.url, settings.database.max_connections)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -44,23 +135,143 @@ async fn configure_postqresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+async fn configure_redis(settings: &Settings) -> RedisConnection {
+    RedisConnection::connect(&settings.redis)
+        .await
         .expect("Failed to get Redis connection")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+// Keeps banned tokens and 2FA codes in Redis when connected, otherwise in Postgres
+// with a purger deleting the expired rows
+fn configure_token_stores(
+    settings: &Settings,
+    pg_pool: &PgPool,
+    redis_conn: Option<&RedisConnection>,
+) -> (
+    BannedStoreType,
+    TwoFACodeStoreType,
+    Option<TokenStorePurger>,
+) {
+    let auth = &settings.auth;
+    match redis_conn {
+        Some(redis_conn) => (
+            Arc::new(RedisBannedTokenStore::new(
+                redis_conn.clone(),
+                auth.token_ttl,
+            )),
+            Arc::new(RedisTwoFACodeStore::new(
+                redis_conn.clone(),
+                auth.two_fa_code_ttl,
+            )),
+            None,
+        ),
+        None => {
+            let banned_token_store = Arc::new(PostgresBannedTokenStore::new(
+                pg_pool.clone(),
+                auth.token_ttl,
+            ));
+            let two_fa_code_store = Arc::new(PostgresTwoFACodeStore::new(
+                pg_pool.clone(),
+                auth.two_fa_code_ttl,
+            ));
+            let purger = TokenStorePurger::new(
+                banned_token_store.clone(),
+                two_fa_code_store.clone(),
+                settings.token_store.purge_interval,
+            );
+            (banned_token_store, two_fa_code_store, Some(purger))
+        }
+    }
+}
+
+// Local development only: capture emails instead of sending them
+fn configure_dev_mailbox(settings: &EmailSettings) -> Option<Arc<DevMailboxEmailClient>> {
+    if settings.providers != ["dev-mailbox"] {
+        return None;
+    }
+    let dev_mailbox = match settings.dev_mailbox_dir.as_ref() {
+        Some(directory) => DevMailboxEmailClient::in_directory(directory),
+        None => DevMailboxEmailClient::in_memory(),
+    };
+    Some(Arc::new(dev_mailbox))
+}
+
+fn configure_email_client(settings: &EmailSettings) -> EmailClientType {
+    let mut providers: Vec<(String, EmailClientType)> = settings
+        .providers
+        .iter()
+        .map(|provider| {
+            (
+                provider.to_owned(),
+                configure_email_provider(provider, settings),
+            )
+        })
+        .collect();
+    if providers.len() == 1 {
+        return providers.remove(0).1;
+    }
+    Arc::new(FailoverEmailClient::new(
+        providers,
+        settings.failover.threshold,
+        settings.failover.cooldown,
+    ))
+}
+
+// Providers are checked by `Settings::load`
+fn configure_email_provider(provider: &str, settings: &EmailSettings) -> EmailClientType {
+    match provider {
+        "smtp" => Arc::new(configure_smtp_email_client(settings)),
+        _ => Arc::new(configure_postmark_email_client(settings)),
+    }
+}
+
+fn configure_sms_client(settings: &SmsSettings) -> SmsClientType {
+    match settings.sender_phone_number() {
+        Some(sender) if settings.provider == "twilio" => {
+            let http_client = Client::builder()
+                .timeout(settings.timeout)
+                .build()
+                .expect("Failed to build HTTP client");
+            Arc::new(TwilioSmsClient::new(
+                settings.twilio.base_url.to_owned(),
+                settings.twilio.account_sid.to_owned(),
+                settings.twilio.auth_token.clone(),
+                sender,
+                http_client,
+            ))
+        }
+        _ => Arc::new(MockSmsClient),
+    }
+}
+
+fn configure_smtp_email_client(settings: &EmailSettings) -> SmtpEmailClient {
+    let smtp = &settings.smtp;
+    let smtp_settings = SmtpSettings {
+        host: smtp.host.to_owned(),
+        port: smtp.port,
+        tls: SmtpTls::parse(&smtp.tls).expect("Invalid SMTP TLS mode"),
+        credentials: smtp.username.clone().map(|username| SmtpCredentials {
+            username,
+            password: smtp.password.clone(),
+        }),
+        timeout: settings.timeout,
+        pool_size: smtp.pool_size,
+    };
+
+    SmtpEmailClient::new(smtp_settings, settings.sender_email())
+        .expect("Failed to build SMTP email client")
+}
+
+fn configure_postmark_email_client(settings: &EmailSettings) -> PostmarkEmailClient {
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(settings.timeout)
         .build()
         .expect("Failed to build HTTP client");
 
     PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(SecretString::from(prod::email_client::SENDER)).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+        settings.postmark.base_url.to_owned(),
+        settings.sender_email(),
+        settings.postmark.auth_token.clone(),
         http_client,
     )
 }
@@ -0,0 +1,344 @@
+use crate::AppState;
+use crate::domain::{
+    AccountStatus, AuditEvent, AuditEventKind, AuditQuery, AuthAPIError, DeliveryEvent,
+    DeliveryEventKind, Email, Password, SecurityNotification, User, UserQuery, UserStoreError,
+};
+use crate::utils::{auth_token, queue_security_notification, validate_token};
+use axum::Json;
+use axum::extract::{Path, Query, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+#[derive(Deserialize)]
+pub struct ListUsersParams {
+    pub search: Option<String>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UserResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    pub status: AccountStatus,
+    #[serde(rename = "tokensRevokedAt")]
+    pub tokens_revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            requires_2fa: user.requires_2fa,
+            is_admin: user.is_admin,
+            status: user.status,
+            tokens_revoked_at: user.tokens_revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListUsersResponse {
+    pub users: Vec<UserResponse>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+    pub total: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub password: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct SetRequires2FARequest {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ListAuditEventsParams {
+    pub email: Option<String>,
+    pub kind: Option<AuditEventKind>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListAuditEventsResponse {
+    pub events: Vec<AuditEvent>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+    pub total: u64,
+}
+
+// Middleware guarding every `/admin` route.
+// The caller must present a valid JWT cookie belonging to a user flagged as admin.
+#[tracing::instrument(name = "Require admin", skip_all)]
+pub async fn require_admin(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let token = auth_token(request.headers(), &jar, &state.settings.cookie)
+        .ok_or(AuthAPIError::MissingToken)?;
+    let claims = validate_token(
+        &token,
+        &state.settings.auth,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::from)?;
+
+    let email =
+        Email::parse(SecretString::from(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+    let user = state
+        .user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    if !user.is_admin {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let query = UserQuery {
+        search: params.search.filter(|search| !search.is_empty()),
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let user_page = state
+        .user_store
+        .list_users(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListUsersResponse {
+        users: user_page
+            .users
+            .into_iter()
+            .map(UserResponse::from)
+            .collect(),
+        page,
+        per_page,
+        total: user_page.total,
+    }))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let user = fetch_user(&state, &email).await?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+#[tracing::instrument(name = "Admin disable user", skip_all)]
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    state
+        .user_store
+        .set_status(&email, AccountStatus::Disabled)
+        .await
+        .map_err(map_user_store_error)?;
+    let user = fetch_user(&state, &email).await?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+#[tracing::instrument(name = "Admin enable user", skip_all)]
+pub async fn enable_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    state
+        .user_store
+        .set_status(&email, AccountStatus::Active)
+        .await
+        .map_err(map_user_store_error)?;
+    let user = fetch_user(&state, &email).await?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+// Replaces the user's password with one chosen by the admin and revokes every
+// token issued so far, forcing the user to sign in again with the new password.
+#[tracing::instrument(name = "Admin reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    state
+        .user_store
+        .update_password(&email, password)
+        .await
+        .map_err(map_user_store_error)?;
+    state
+        .user_store
+        .revoke_tokens(&email)
+        .await
+        .map_err(map_user_store_error)?;
+    let user = fetch_user(&state, &email).await?;
+    queue_security_notification(
+        &state.email_outbox,
+        &email,
+        SecurityNotification::PasswordChanged,
+    )
+    .await;
+    Ok(Json(UserResponse::from(user)))
+}
+
+#[tracing::instrument(name = "Admin set requires 2FA", skip_all)]
+pub async fn set_requires_2fa(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+    Json(request): Json<SetRequires2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let previous = fetch_user(&state, &email).await?;
+    state
+        .user_store
+        .set_requires_2fa(&email, request.requires_2fa)
+        .await
+        .map_err(map_user_store_error)?;
+    let user = fetch_user(&state, &email).await?;
+    if previous.requires_2fa && !user.requires_2fa {
+        queue_security_notification(
+            &state.email_outbox,
+            &email,
+            SecurityNotification::TwoFADisabled,
+        )
+        .await;
+    }
+    Ok(Json(UserResponse::from(user)))
+}
+
+#[tracing::instrument(name = "Admin revoke tokens", skip_all)]
+pub async fn revoke_tokens(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    state
+        .user_store
+        .revoke_tokens(&email)
+        .await
+        .map_err(map_user_store_error)?;
+    let user = fetch_user(&state, &email).await?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+// Lets email 2FA codes go to an address again after a hard bounce or spam complaint,
+// once the user has confirmed their mailbox works. A later bounce suppresses it again.
+#[tracing::instrument(name = "Admin clear email suppression", skip_all)]
+pub async fn clear_email_suppression(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let user = fetch_user(&state, &email).await?;
+    let mut event = DeliveryEvent::new(
+        email.as_ref().expose_secret(),
+        DeliveryEventKind::SuppressionCleared,
+        Utc::now(),
+    );
+    event.detail = Some("cleared by admin".to_owned());
+    state
+        .email_delivery_store
+        .record_event(event)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+#[tracing::instrument(name = "Admin list audit events", skip_all)]
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    Query(params): Query<ListAuditEventsParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let query = AuditQuery {
+        email: params.email.filter(|email| !email.is_empty()),
+        kind: params.kind,
+        from: params.from,
+        to: params.to,
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let audit_page = state
+        .audit_sink
+        .query(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListAuditEventsResponse {
+        events: audit_page.events,
+        page,
+        per_page,
+        total: audit_page.total,
+    }))
+}
+
+fn parse_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(SecretString::from(email)).map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+async fn fetch_user(state: &AppState, email: &Email) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .get_user(email)
+        .await
+        .map_err(map_user_store_error)
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
@@ -0,0 +1,85 @@
+use crate::AppState;
+use crate::domain::AuthAPIError;
+use crate::utils::{bearer_token, csrf_cookie};
+use axum::Json;
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::CookieJar;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+// Header that must echo the CSRF cookie on cookie-authenticated state-changing requests
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+const CSRF_TOKEN_LENGTH: usize = 43;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsrfTokenResponse {
+    #[serde(rename = "csrfToken")]
+    pub csrf_token: String,
+}
+
+// Returns the CSRF token for this browser, issuing a new token cookie if there isn't one yet
+#[tracing::instrument(name = "Get CSRF token", skip_all)]
+pub async fn get_csrf_token(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, impl IntoResponse) {
+    let settings = &state.settings;
+    let existing = jar
+        .get(&settings.csrf.cookie_name)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| token.len() == CSRF_TOKEN_LENGTH);
+    let (jar, csrf_token) = match existing {
+        Some(token) => (jar, token),
+        None => {
+            let token = generate_csrf_token();
+            let cookie = csrf_cookie(token.clone(), &settings.csrf, &settings.cookie);
+            (jar.add(cookie), token)
+        }
+    };
+    (jar, Json(CsrfTokenResponse { csrf_token }))
+}
+
+// Double-submit check for routes authenticated by the auth cookie. A cross-site form can make
+// the browser send cookies, but it can't read the CSRF cookie to copy it into the header.
+// Requests using a Bearer token don't rely on ambient credentials and are let through.
+pub async fn require_csrf_token(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let safe_method = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let cookie_authenticated = jar.get(&state.settings.cookie.name).is_some();
+    if safe_method || !cookie_authenticated || bearer_token(request.headers()).is_some() {
+        return Ok(next.run(request).await);
+    }
+
+    let expected = jar
+        .get(&state.settings.csrf.cookie_name)
+        .filter(|cookie| !cookie.value().is_empty())
+        .ok_or(AuthAPIError::InvalidCsrfToken)?;
+    let provided = request
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .ok_or(AuthAPIError::InvalidCsrfToken)?;
+    if !bool::from(provided.as_bytes().ct_eq(expected.value().as_bytes())) {
+        return Err(AuthAPIError::InvalidCsrfToken);
+    }
+    Ok(next.run(request).await)
+}
+
+fn generate_csrf_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(CSRF_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,61 @@
+use crate::domain::AuthAPIError;
+use crate::{AppState, CapturedEmail};
+use askama::Template;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
+use uuid::Uuid;
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxPage<'a> {
+    emails: &'a [CapturedEmail],
+}
+
+// Lists captured emails newest first, as an HTML page or as JSON when the client asks for it.
+// Only routed when the dev mailbox is the configured email client.
+#[tracing::instrument(name = "List dev mailbox", skip_all)]
+pub async fn list_dev_mailbox(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AuthAPIError> {
+    let Some(mailbox) = state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let emails = mailbox
+        .list()
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+    if wants_json {
+        return Ok(Json(emails).into_response());
+    }
+    let page = MailboxPage { emails: &emails }
+        .render()
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(Html(page).into_response())
+}
+
+// Renders the HTML body of one captured email as the recipient would see it
+#[tracing::instrument(name = "Show dev mailbox email", skip_all)]
+pub async fn show_dev_mailbox_email(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AuthAPIError> {
+    let Some(mailbox) = state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    match mailbox
+        .get(id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+    {
+        Some(email) => Ok(Html(email.html_body).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
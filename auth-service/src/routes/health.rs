@@ -0,0 +1,159 @@
+use crate::AppState;
+use crate::domain::DependencyCheck;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LivenessResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    // "ready", or "unavailable" when a critical dependency is down
+    pub status: String,
+    pub checks: BTreeMap<String, DependencyStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    // "up" or "down"
+    pub status: String,
+    pub critical: bool,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+}
+
+// The process is up and serving requests, whatever the state of its dependencies
+pub async fn liveness() -> impl IntoResponse {
+    Json(LivenessResponse {
+        status: "alive".to_owned(),
+    })
+}
+
+// Pings every dependency concurrently, answering 503 when a critical one is down so the
+// instance is taken out of rotation until it recovers
+#[tracing::instrument(name = "Readiness check", skip_all)]
+pub async fn readiness(State(state): State<AppState>) -> impl IntoResponse {
+    let checks = check_dependencies(
+        state.health_checks.clone(),
+        state.settings.health.check_timeout,
+    )
+    .await;
+    let ready = checks
+        .values()
+        .all(|check| !check.critical || check.status == "up");
+    let (status_code, status) = match ready {
+        true => (StatusCode::OK, "ready"),
+        false => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+    };
+    let response = ReadinessResponse {
+        status: status.to_owned(),
+        checks,
+    };
+    (status_code, Json(response))
+}
+
+async fn check_dependencies(
+    checks: Vec<DependencyCheck>,
+    timeout: Duration,
+) -> BTreeMap<String, DependencyStatus> {
+    let mut tasks = JoinSet::new();
+    for dependency in checks {
+        tasks.spawn(async move {
+            let started = Instant::now();
+            let result = tokio::time::timeout(timeout, dependency.check.check()).await;
+            // The readiness endpoint is public, so why a dependency is down is only logged
+            let up = match result {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    tracing::warn!(dependency = %dependency.name, error = ?e, "health check failed");
+                    false
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        dependency = %dependency.name,
+                        "health check got no response within {timeout:?}"
+                    );
+                    false
+                }
+            };
+            let status = DependencyStatus {
+                status: if up { "up" } else { "down" }.to_owned(),
+                critical: dependency.critical,
+                latency_ms: started.elapsed().as_millis() as u64,
+            };
+            (dependency.name, status)
+        });
+    }
+    tasks.join_all().await.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::HealthCheck;
+    use color_eyre::eyre::{Result, eyre};
+    use std::sync::Arc;
+
+    enum FakeCheck {
+        Up,
+        Down,
+        Hangs,
+    }
+
+    #[async_trait::async_trait]
+    impl HealthCheck for FakeCheck {
+        async fn check(&self) -> Result<()> {
+            match self {
+                FakeCheck::Up => Ok(()),
+                FakeCheck::Down => Err(eyre!("connection refused")),
+                FakeCheck::Hangs => std::future::pending().await,
+            }
+        }
+    }
+
+    fn dependency(name: &str, critical: bool, check: FakeCheck) -> DependencyCheck {
+        DependencyCheck {
+            name: name.to_owned(),
+            critical,
+            check: Arc::new(check),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_every_dependency() {
+        let checks = check_dependencies(
+            vec![
+                dependency("postgres", true, FakeCheck::Up),
+                dependency("email", false, FakeCheck::Down),
+            ],
+            Duration::from_secs(1),
+        )
+        .await;
+
+        assert_eq!(checks["postgres"].status, "up");
+        assert!(checks["postgres"].critical);
+        assert_eq!(checks["email"].status, "down");
+        assert!(!checks["email"].critical);
+    }
+
+    #[tokio::test]
+    async fn test_reports_hanging_dependency_as_down() {
+        let started = Instant::now();
+        let checks = check_dependencies(
+            vec![dependency("redis", true, FakeCheck::Hangs)],
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert_eq!(checks["redis"].status, "down");
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
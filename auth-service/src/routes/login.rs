@@ -1,9 +1,16 @@
-use crate::domain::{AuthAPIError, Email, Password};
+use crate::domain::{
+    AccountStatus, AuditEvent, AuditEventKind, AuthAPIError, Email, EmailDeliveryError,
+    OutboxEmail, Password, RequestMetadata, TwoFAChannel, User,
+};
+use crate::email_templates::{Locale, two_fa_code_email, two_fa_code_sms};
+use crate::settings::Settings;
+use crate::utils::{metrics, notify_if_new_device, record_audit_event};
 use crate::{AppState, LoginAttemptId, TwoFACode, utils};
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::{Json, response::IntoResponse};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use color_eyre::Result;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
@@ -28,12 +35,16 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    // Where the code was sent, so the client can tell the user where to look
+    #[serde(rename = "twoFAChannel")]
+    pub channel: TwoFAChannel,
 }
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     state: State<AppState>,
     jar: CookieJar,
+    metadata: RequestMetadata,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(SecretString::from(request.email)) {
@@ -45,9 +56,16 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = state.user_store.read().await;
+    let user_store = &state.user_store;
 
     if user_store.validate_user(&email, &password).await.is_err() {
+        record_audit_event(
+            &state.audit_sink,
+            AuditEvent::new(AuditEventKind::LoginFailed, Some(&email), &metadata)
+                .with_detail("incorrect credentials"),
+        )
+        .await;
+        metrics().record_login("failure");
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
@@ -56,50 +74,158 @@ pub async fn login(
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     };
 
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    let status_error = match user.status {
+        AccountStatus::Active => None,
+        AccountStatus::Disabled => Some(("account disabled", AuthAPIError::AccountDisabled)),
+        AccountStatus::PendingVerification => Some((
+            "account pending verification",
+            AuthAPIError::AccountPendingVerification,
+        )),
+    };
+    if let Some((detail, error)) = status_error {
+        record_audit_event(
+            &state.audit_sink,
+            AuditEvent::new(AuditEventKind::LoginFailed, Some(&email), &metadata)
+                .with_detail(detail),
+        )
+        .await;
+        metrics().record_login("failure");
+        return (jar, Err(error));
     }
+
+    let (jar, result, kind) = match user.requires_2fa {
+        true => {
+            let (jar, result) = handle_2fa(&user, &state, jar).await;
+            (jar, result, AuditEventKind::TwoFASent)
+        }
+        false => {
+            let (jar, result) = handle_no_2fa(&user, &state.settings, jar).await;
+            (jar, result, AuditEventKind::LoginSucceeded)
+        }
+    };
+    if result.is_ok() {
+        if kind == AuditEventKind::LoginSucceeded {
+            notify_if_new_device(&state, &email, &metadata).await;
+            metrics().record_login("success");
+        } else {
+            metrics().record_login("two_fa_required");
+        }
+        record_audit_event(
+            &state.audit_sink,
+            AuditEvent::new(kind, Some(&email), &metadata),
+        )
+        .await;
+    }
+    (jar, result)
 }
 
 #[tracing::instrument(name = "Handling 2fa", skip_all)]
 async fn handle_2fa(
-    email: &Email,
+    user: &User,
     state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
+    let email = &user.email;
+    // Refuse up front when the provider has told us the address bounces, instead of
+    // issuing a code the user will never receive
+    if user.sms_phone_number().is_none()
+        && let Err(e) = ensure_deliverable(state, email).await
+    {
+        return (jar, Err(e));
+    }
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
-    let mut two_fa_store = state.two_fa_code_store.write().await;
+    let two_fa_store = &state.two_fa_code_store;
     if let Err(e) = two_fa_store
         .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
     {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
-    //Send 2FA code via the email client. Return `AuthAPIError::UnexpectedError` if the operation fails.
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "2FA_Code", two_fa_code.as_ref().expose_secret())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    // Users who opted into SMS get a text. If that fails, fall back to email so they
+    // aren't locked out.
+    if let Some(phone_number) = user.sms_phone_number() {
+        let body = two_fa_code_sms(two_fa_code.as_ref().expose_secret(), Locale::default());
+        match state.sms_client.send_sms(phone_number, &body).await {
+            Ok(()) => {
+                metrics().record_two_fa_code_issued("sms");
+                return (
+                    jar,
+                    Ok(two_fa_response(&login_attempt_id, TwoFAChannel::Sms)),
+                );
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, "failed to text 2FA code, falling back to email");
+                if let Err(e) = ensure_deliverable(state, email).await {
+                    return (jar, Err(e));
+                }
+            }
+        }
+    }
+    // Send the 2FA code right away since the user is waiting for it. If the provider fails,
+    // hand the email to the outbox instead of failing the login, unless retrying can't help.
+    let message = match two_fa_code_email(two_fa_code.as_ref().expose_secret(), Locale::default()) {
+        Ok(message) => message,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    if let Err(e) = state.email_client.send_email(email, &message).await {
+        let permanent = EmailDeliveryError::is_permanent(&e);
+        metrics().record_email_send_failure("login", permanent);
+        if permanent {
+            tracing::warn!(error = ?e, "2FA code was rejected by the email provider");
+            return (jar, Err(AuthAPIError::EmailUndeliverable));
+        }
+        tracing::warn!(error = ?e, "failed to send 2FA code, queueing it for retry");
+        // The code is useless once it expires, so don't deliver it after that
+        let ttl = match chrono::Duration::from_std(state.settings.auth.two_fa_code_ttl) {
+            Ok(ttl) => ttl,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        };
+        let queued = OutboxEmail {
+            idempotency_key: format!("two-fa:{}", login_attempt_id.as_ref().expose_secret()),
+            recipient: email.clone(),
+            message,
+            expires_at: Some(Utc::now() + ttl),
+        };
+        if let Err(e) = state.email_outbox.enqueue(queued).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
     }
+    metrics().record_two_fa_code_issued("email");
+    (
+        jar,
+        Ok(two_fa_response(&login_attempt_id, TwoFAChannel::Email)),
+    )
+}
+
+async fn ensure_deliverable(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    match state.email_delivery_store.is_undeliverable(email).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(AuthAPIError::EmailUndeliverable),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+fn two_fa_response(
+    login_attempt_id: &LoginAttemptId,
+    channel: TwoFAChannel,
+) -> (StatusCode, Json<LoginResponse>) {
     // Return a TwoFactorAuthResponse. The message should be "2FA required".
-    // The login attempt ID should be "123456". We will replace this hard-coded login attempt ID soon!
     let response = LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+        channel,
     });
-    (jar, Ok((StatusCode::PARTIAL_CONTENT, Json(response))))
+    (StatusCode::PARTIAL_CONTENT, Json(response))
 }
 
 #[tracing::instrument(name = "Handling no 2fa", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    settings: &Settings,
     jar: CookieJar,
 ) -> (
     CookieJar,
@@ -107,7 +233,7 @@ async fn handle_no_2fa(
 ) {
     // Call the generate_auth_cookie function defined in the auth module.
     // If the function call fails, return AuthAPIError::UnexpectedError.
-    let auth_cookie = match utils::generate_auth_cookie(email) {
+    let auth_cookie = match utils::generate_auth_cookie(user, &settings.auth, &settings.cookie) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
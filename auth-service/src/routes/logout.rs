@@ -1,45 +1,62 @@
 use crate::AppState;
-use crate::domain::AuthAPIError;
-use crate::utils::{JWT_COOKIE_NAME, validate_token};
+use crate::domain::{AuditEvent, AuditEventKind, AuthAPIError, Email, RequestMetadata};
+use crate::utils::{auth_removal_cookie, auth_token, record_audit_event, validate_token};
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
-use axum_extra::extract::cookie::Cookie;
-use color_eyre::Result;
-use color_eyre::eyre::ContextCompat;
 use secrecy::SecretString;
 
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     state: State<AppState>,
+    headers: HeaderMap,
     jar: CookieJar,
-) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    // Retrieve JWT cookie from the `CookieJar`
-    // Return AuthAPIError::MissingToken if the cookie is not found
-    let cookie = jar
-        .get(JWT_COOKIE_NAME)
-        .wrap_err("No Cookie found")
-        .map_err(|_| AuthAPIError::MissingToken)?;
+    metadata: RequestMetadata,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    // Retrieve the JWT from a Bearer header or the `CookieJar`
+    // Return AuthAPIError::MissingToken if there is none
+    let Some(token) = auth_token(&headers, &jar, &state.settings.cookie) else {
+        return (jar, Err(AuthAPIError::MissingToken));
+    };
+    // Remove the token cookie even when the token is rejected below, so a disabled
+    // account can still clear it
+    let jar = jar.remove(auth_removal_cookie(&state.settings.cookie));
 
-    let token = SecretString::from(cookie.value());
     // Validate JWT token by calling `validate_token` from the auth service.
     // If the token is valid, you can ignore the returned claims for now.
-    // Return AuthAPIError::InvalidToken is validation fails.
-    let _login_result = validate_token(&token, state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    // Return AuthAPIError::InvalidToken is validation fails,
+    // or AuthAPIError::AccountDisabled / AccountPendingVerification if the account is not active.
+    let claims = match validate_token(
+        &token,
+        &state.settings.auth,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(e) => {
+            record_audit_event(
+                &state.audit_sink,
+                AuditEvent::new(AuditEventKind::TokenRejected, None, &metadata)
+                    .with_detail(e.to_string()),
+            )
+            .await;
+            return (jar, Err(AuthAPIError::from(e)));
+        }
+    };
 
-    state
-        .banned_token_store
-        .write()
-        .await
-        .add_token(&token)
-        .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if let Err(e) = state.banned_token_store.add_token(&token).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
 
-    // remove token in cookie
-    let updated_jar = jar.remove(Cookie::from(JWT_COOKIE_NAME));
+    let email = Email::parse(SecretString::from(claims.sub)).ok();
+    record_audit_event(
+        &state.audit_sink,
+        AuditEvent::new(AuditEventKind::Logout, email.as_ref(), &metadata),
+    )
+    .await;
 
-    Ok((updated_jar, StatusCode::OK))
+    (jar, Ok(StatusCode::OK))
 }
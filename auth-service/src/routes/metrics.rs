@@ -0,0 +1,11 @@
+use crate::utils::metrics;
+use axum::http::header;
+use axum::response::IntoResponse;
+
+// Prometheus scrape endpoint
+pub async fn get_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        metrics().render(),
+    )
+}
@@ -1,11 +1,25 @@
+mod admin;
+mod csrf;
+mod dev_mailbox;
+mod health;
 mod login;
 mod logout;
+mod metrics;
+mod phone_number;
 mod signup;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
 
+pub use admin::*;
+pub use csrf::*;
+pub use dev_mailbox::*;
+pub use health::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use phone_number::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
+pub use webhooks::*;
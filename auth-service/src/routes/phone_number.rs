@@ -0,0 +1,195 @@
+use crate::AppState;
+use crate::domain::{
+    AuthAPIError, Email, LoginAttemptId, PhoneNumber, PhoneVerification,
+    PhoneVerificationStoreError, TwoFAChannel, TwoFACode, UserStoreError,
+};
+use crate::email_templates::{Locale, two_fa_code_sms};
+use crate::utils::{auth_token, metrics, validate_token};
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use color_eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct SetPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: SecretString,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetPhoneNumberResponse {
+    pub message: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetTwoFAChannelRequest {
+    #[serde(rename = "twoFAChannel")]
+    pub channel: TwoFAChannel,
+}
+
+// Stores an unverified phone number for the signed-in user and texts it a code.
+// The code is kept in the phone verification store, apart from login 2FA codes, and
+// confirmed through `/phone-number/verify`. Users may only be sent so many codes.
+#[tracing::instrument(name = "Set phone number", skip_all)]
+pub async fn set_phone_number(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(request): Json<SetPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&state, &headers, &jar).await?;
+    let phone_number =
+        PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let login_attempt_id = LoginAttemptId::default();
+    let two_fa_code = TwoFACode::default();
+    let verification = PhoneVerification {
+        phone_number: phone_number.clone(),
+        login_attempt_id: login_attempt_id.clone(),
+        code: two_fa_code.clone(),
+    };
+    match state
+        .phone_verification_store
+        .add_code(email.clone(), verification)
+        .await
+    {
+        Ok(()) => {}
+        Err(PhoneVerificationStoreError::TooManyCodesSent) => {
+            return Err(AuthAPIError::TooManyVerificationCodes);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state
+        .user_store
+        .set_phone_number(&email, phone_number.clone())
+        .await
+        .map_err(map_user_store_error)?;
+    let body = two_fa_code_sms(two_fa_code.as_ref().expose_secret(), Locale::default());
+    state
+        .sms_client
+        .send_sms(&phone_number, &body)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    metrics().record_two_fa_code_issued("sms");
+
+    Ok(Json(SetPhoneNumberResponse {
+        message: "Verification code sent".to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+    }))
+}
+
+#[tracing::instrument(name = "Verify phone number", skip_all)]
+pub async fn verify_phone_number(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&state, &headers, &jar).await?;
+    let login_attempt_id = LoginAttemptId::parse(SecretString::from(request.login_attempt_id))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let two_fa_code = TwoFACode::parse(SecretString::from(request.two_fa_code))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = state
+        .user_store
+        .get_user(&email)
+        .await
+        .map_err(map_user_store_error)?;
+    let phone_verification_store = &state.phone_verification_store;
+    // The code only proves the number it was texted to, which must still be the user's
+    let mut verified = match phone_verification_store.get_code(&email).await {
+        Ok(verification) => {
+            verification.login_attempt_id == login_attempt_id
+                && verification.code == two_fa_code
+                && user.phone_number.as_ref() == Some(&verification.phone_number)
+        }
+        Err(_) => false,
+    };
+    // Only the request that removes the code may use it
+    if verified {
+        match phone_verification_store.remove_code(&email).await {
+            Ok(()) => {}
+            Err(PhoneVerificationStoreError::VerificationNotFound) => verified = false,
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+    metrics().record_two_fa_code_verified(verified);
+    if !verified {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    state
+        .user_store
+        .mark_phone_verified(&email)
+        .await
+        .map_err(map_user_store_error)?;
+
+    Ok(StatusCode::OK)
+}
+
+// Chooses where 2FA codes are sent. SMS requires a verified phone number.
+#[tracing::instrument(name = "Set 2FA channel", skip_all)]
+pub async fn set_two_fa_channel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(request): Json<SetTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&state, &headers, &jar).await?;
+    let user_store = &state.user_store;
+    if request.channel == TwoFAChannel::Sms {
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(map_user_store_error)?;
+        if user.phone_number.is_none() || !user.phone_verified {
+            return Err(AuthAPIError::PhoneNumberNotVerified);
+        }
+    }
+    user_store
+        .set_two_fa_channel(&email, request.channel)
+        .await
+        .map_err(map_user_store_error)?;
+
+    Ok(StatusCode::OK)
+}
+
+// Resolves the signed-in user from a Bearer token or the JWT cookie
+async fn authenticate(
+    state: &AppState,
+    headers: &HeaderMap,
+    jar: &CookieJar,
+) -> Result<Email, AuthAPIError> {
+    let token =
+        auth_token(headers, jar, &state.settings.cookie).ok_or(AuthAPIError::MissingToken)?;
+    let claims = validate_token(
+        &token,
+        &state.settings.auth,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::from)?;
+    Email::parse(SecretString::from(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
@@ -1,5 +1,9 @@
 use crate::AppState;
-use crate::domain::{AuthAPIError, Email, Password, User};
+use crate::domain::{
+    AuditEvent, AuditEventKind, AuthAPIError, Email, Password, RequestMetadata, User,
+    UserStoreError,
+};
+use crate::utils::record_audit_event;
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -22,24 +26,34 @@ pub struct SignupResponse {
 pub async fn signup(
     // Use Axum's state extractor to pass in AppState
     state: State<AppState>,
+    metadata: RequestMetadata,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let password =
         Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
     // Create a new `User` instance using data in the `request`
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User::new(email.clone(), password, request.requires_2fa);
 
-    let mut user_store = state.user_store.write().await;
+    let user_store = &state.user_store;
     if user_store.get_user(&user.email).await.is_ok() {
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
     // Add `user` to the `user_store`. Simply unwrap the returned `Result` enum type for now.
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    // A concurrent signup for the same email may have won since the check above
+    match user_store.add_user(user).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
 
+    record_audit_event(
+        &state.audit_sink,
+        AuditEvent::new(AuditEventKind::Signup, Some(&email), &metadata),
+    )
+    .await;
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
@@ -1,5 +1,8 @@
-use crate::utils::generate_auth_cookie;
-use crate::{AppState, AuthAPIError, Email, LoginAttemptId, TwoFACode};
+use crate::utils::{generate_auth_cookie, metrics, notify_if_new_device, record_audit_event};
+use crate::{
+    AccountStatus, AppState, AuditEvent, AuditEventKind, AuthAPIError, Email, LoginAttemptId,
+    RequestMetadata, TwoFACode, TwoFACodeStoreError,
+};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -22,6 +25,7 @@ pub struct Verify2FARequest {
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
+    metadata: RequestMetadata,
     Json(request): Json<Verify2FARequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let email = Email::parse(SecretString::from(request.email.as_ref().to_owned()))
@@ -31,14 +35,20 @@ pub async fn verify_2fa(
     let two_fa_code = TwoFACode::parse(SecretString::from(request.two_fa_code))
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    let two_fa_code_store = &state.two_fa_code_store;
 
     // Call `two_fa_code_store.get_code`. If the call fails
     // return a `AuthAPIError::IncorrectCredentials`.
-    let code_tuple = two_fa_code_store
-        .get_code(&email)
-        .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let Ok(code_tuple) = two_fa_code_store.get_code(&email).await else {
+        record_audit_event(
+            &state.audit_sink,
+            AuditEvent::new(AuditEventKind::TwoFAFailed, Some(&email), &metadata)
+                .with_detail("no pending login attempt"),
+        )
+        .await;
+        metrics().record_two_fa_code_verified(false);
+        return Err(AuthAPIError::IncorrectCredentials);
+    };
 
     // Validate that the `login_attempt_id` and `two_fa_code`
     // in the request body matches values in the `code_tuple`.
@@ -47,14 +57,49 @@ pub async fn verify_2fa(
         (store_login_attempt_id, store_two_fa_code)
             if store_login_attempt_id == login_attempt_id && store_two_fa_code == two_fa_code =>
         {
-            two_fa_code_store
-                .remove_code(&email)
+            // Removing the code is what consumes it, so of two requests racing with the same
+            // code only the one that removes it signs in
+            match two_fa_code_store.remove_code(&email).await {
+                Ok(()) => metrics().record_two_fa_code_verified(true),
+                Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+                    metrics().record_two_fa_code_verified(false);
+                    return Err(AuthAPIError::IncorrectCredentials);
+                }
+                Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+            // The account may have been disabled between login and 2FA verification
+            let user = state
+                .user_store
+                .get_user(&email)
                 .await
                 .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-            let cookie = generate_auth_cookie(&email).map_err(AuthAPIError::UnexpectedError)?;
+            match user.status {
+                AccountStatus::Active => {}
+                AccountStatus::Disabled => return Err(AuthAPIError::AccountDisabled),
+                AccountStatus::PendingVerification => {
+                    return Err(AuthAPIError::AccountPendingVerification);
+                }
+            }
+            let cookie = generate_auth_cookie(&user, &state.settings.auth, &state.settings.cookie)
+                .map_err(AuthAPIError::UnexpectedError)?;
             let updated_jar = jar.add(cookie);
+            notify_if_new_device(&state, &email, &metadata).await;
+            record_audit_event(
+                &state.audit_sink,
+                AuditEvent::new(AuditEventKind::TwoFAVerified, Some(&email), &metadata),
+            )
+            .await;
             Ok((updated_jar, StatusCode::OK.into_response()))
         }
-        _ => Err(AuthAPIError::IncorrectCredentials),
+        _ => {
+            record_audit_event(
+                &state.audit_sink,
+                AuditEvent::new(AuditEventKind::TwoFAFailed, Some(&email), &metadata)
+                    .with_detail("incorrect code or login attempt id"),
+            )
+            .await;
+            metrics().record_two_fa_code_verified(false);
+            Err(AuthAPIError::IncorrectCredentials)
+        }
     }
 }
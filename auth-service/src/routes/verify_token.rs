@@ -1,6 +1,6 @@
 use crate::AppState;
-use crate::domain::AuthAPIError;
-use crate::utils::validate_token;
+use crate::domain::{AuditEvent, AuditEventKind, AuthAPIError, RequestMetadata};
+use crate::utils::{record_audit_event, validate_token};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -17,20 +17,28 @@ pub struct VerifyTokenRequest {
 #[tracing::instrument(name = "Verify Token", skip_all)]
 pub async fn verify_token(
     state: State<AppState>,
+    metadata: RequestMetadata,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let token = SecretString::from(request.token);
-    validate_token(&token, state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
-
-    match state
-        .banned_token_store
-        .read()
-        .await
-        .contains_token(&token)
-        .await
+    if let Err(e) = validate_token(
+        &token,
+        &state.settings.auth,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
     {
+        record_audit_event(
+            &state.audit_sink,
+            AuditEvent::new(AuditEventKind::TokenRejected, None, &metadata)
+                .with_detail(e.to_string()),
+        )
+        .await;
+        return Err(AuthAPIError::from(e));
+    }
+
+    match state.banned_token_store.contains_token(&token).await {
         Ok(true) => Err(AuthAPIError::InvalidToken),
         Ok(false) => Ok(StatusCode::OK.into_response()),
         Err(_) => Err(AuthAPIError::UnexpectedError(eyre!("oh no"))),
@@ -0,0 +1,172 @@
+use crate::AppState;
+use crate::domain::{AuthAPIError, DeliveryEvent, DeliveryEventKind};
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+pub const POSTMARK_WEBHOOK_TOKEN_HEADER: &str = "X-Postmark-Webhook-Token";
+
+// Bounce types that mean the address will never accept mail. Everything else
+// (soft bounces, transient failures, auto-responders) is recorded as a soft bounce.
+const HARD_BOUNCE_TYPES: [&str; 3] = ["HardBounce", "BadEmailAddress", "ManuallyDeactivated"];
+
+// The subset of Postmark's webhook payloads we care about, keyed on `RecordType`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "RecordType")]
+pub enum PostmarkWebhook {
+    Bounce {
+        #[serde(rename = "Type")]
+        bounce_type: String,
+        #[serde(rename = "Email")]
+        email: String,
+        #[serde(rename = "BouncedAt")]
+        bounced_at: DateTime<Utc>,
+        #[serde(rename = "MessageID")]
+        message_id: Option<String>,
+        #[serde(rename = "Description")]
+        description: Option<String>,
+    },
+    SpamComplaint {
+        #[serde(rename = "Email")]
+        email: String,
+        #[serde(rename = "BouncedAt")]
+        bounced_at: DateTime<Utc>,
+        #[serde(rename = "MessageID")]
+        message_id: Option<String>,
+    },
+    Delivery {
+        #[serde(rename = "Recipient")]
+        recipient: String,
+        #[serde(rename = "DeliveredAt")]
+        delivered_at: DateTime<Utc>,
+        #[serde(rename = "MessageID")]
+        message_id: Option<String>,
+        #[serde(rename = "Details")]
+        details: Option<String>,
+    },
+    // Opens, clicks and subscription changes are acknowledged and ignored
+    #[serde(other)]
+    Other,
+}
+
+impl PostmarkWebhook {
+    fn into_delivery_event(self) -> Option<DeliveryEvent> {
+        match self {
+            Self::Bounce {
+                bounce_type,
+                email,
+                bounced_at,
+                message_id,
+                description,
+            } => {
+                let kind = match HARD_BOUNCE_TYPES.contains(&bounce_type.as_str()) {
+                    true => DeliveryEventKind::HardBounce,
+                    false => DeliveryEventKind::SoftBounce,
+                };
+                let mut event = DeliveryEvent::new(&email, kind, bounced_at);
+                event.message_id = message_id;
+                event.detail = description;
+                Some(event)
+            }
+            Self::SpamComplaint {
+                email,
+                bounced_at,
+                message_id,
+            } => {
+                let mut event =
+                    DeliveryEvent::new(&email, DeliveryEventKind::SpamComplaint, bounced_at);
+                event.message_id = message_id;
+                Some(event)
+            }
+            Self::Delivery {
+                recipient,
+                delivered_at,
+                message_id,
+                details,
+            } => {
+                let mut event =
+                    DeliveryEvent::new(&recipient, DeliveryEventKind::Delivered, delivered_at);
+                event.message_id = message_id;
+                event.detail = details;
+                Some(event)
+            }
+            Self::Other => None,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Postmark webhook", skip_all)]
+pub async fn postmark_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(webhook): Json<PostmarkWebhook>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Without a configured token every webhook is rejected
+    let expected = state
+        .settings
+        .email
+        .postmark
+        .webhook_token
+        .as_ref()
+        .ok_or(AuthAPIError::InvalidToken)?;
+    let provided = headers
+        .get(POSTMARK_WEBHOOK_TOKEN_HEADER)
+        .ok_or(AuthAPIError::MissingToken)?;
+    if !bool::from(
+        provided
+            .as_bytes()
+            .ct_eq(expected.expose_secret().as_bytes()),
+    ) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    if let Some(event) = webhook.into_delivery_event() {
+        tracing::info!(kind = event.kind.as_str(), "recording email delivery event");
+        state
+            .email_delivery_store
+            .record_event(event)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn should_classify_bounce_types() {
+        let webhook = |bounce_type: &str| {
+            serde_json::from_value::<PostmarkWebhook>(json!({
+                "RecordType": "Bounce",
+                "Type": bounce_type,
+                "Email": "John@Example.com",
+                "BouncedAt": "2019-11-05T16:33:54.9070259Z",
+                "MessageID": "00000000-0000-0000-0000-000000000000",
+                "Description": "The server was unable to deliver your message"
+            }))
+            .unwrap()
+            .into_delivery_event()
+            .unwrap()
+        };
+
+        let hard = webhook("HardBounce");
+        assert_eq!(hard.kind, DeliveryEventKind::HardBounce);
+        assert_eq!(hard.recipient, "john@example.com");
+        assert_eq!(webhook("SoftBounce").kind, DeliveryEventKind::SoftBounce);
+    }
+
+    #[test]
+    fn should_ignore_unhandled_record_types() {
+        let webhook =
+            serde_json::from_value::<PostmarkWebhook>(json!({ "RecordType": "Open" })).unwrap();
+        assert!(webhook.into_delivery_event().is_none());
+    }
+}
@@ -1,7 +1,23 @@
+mod postgres_audit_sink;
+mod postgres_banned_token_store;
+mod postgres_email_delivery_store;
+mod postgres_email_outbox;
+mod postgres_known_device_store;
+mod postgres_phone_verification_store;
+mod postgres_two_fa_code_store;
 mod postgres_user_store;
 mod redis_banned_token_store;
+mod redis_connection;
 mod redis_two_fa_code_store;
 
+pub use postgres_audit_sink::*;
+pub use postgres_banned_token_store::*;
+pub use postgres_email_delivery_store::*;
+pub use postgres_email_outbox::*;
+pub use postgres_known_device_store::*;
+pub use postgres_phone_verification_store::*;
+pub use postgres_two_fa_code_store::*;
 pub use postgres_user_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_connection::*;
 pub use redis_two_fa_code_store::*;
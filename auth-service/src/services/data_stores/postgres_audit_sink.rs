@@ -0,0 +1,118 @@
+use crate::utils::metrics;
+use crate::{AuditEvent, AuditEventKind, AuditPage, AuditQuery, AuditSink, AuditSinkError};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use sqlx::PgPool;
+
+pub struct PostgresAuditSink {
+    pool: PgPool,
+}
+
+impl PostgresAuditSink {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditSink for PostgresAuditSink {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditSinkError> {
+        let _timer = metrics().time_store_operation("postgres_audit_sink", "record");
+        sqlx::query!(
+            "insert into audit_events (occurred_at, kind, email, ip, user_agent, request_id, detail)
+            values ($1, $2, $3, $4, $5, $6, $7)",
+            event.occurred_at,
+            event.kind.as_str(),
+            event.email,
+            event.ip,
+            event.user_agent,
+            event.request_id,
+            event.detail
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(&self, query: &AuditQuery) -> Result<AuditPage, AuditSinkError> {
+        let _timer = metrics().time_store_operation("postgres_audit_sink", "query");
+        let kind = query.kind.map(|kind| kind.as_str());
+        let limit =
+            i64::try_from(query.limit).map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+        let offset =
+            i64::try_from(query.offset).map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+
+        let total = sqlx::query_scalar!(
+            r#"select count(*) as "count!" from audit_events
+            where ($1::text is null or email = $1)
+              and ($2::text is null or kind = $2)
+              and ($3::timestamptz is null or occurred_at >= $3)
+              and ($4::timestamptz is null or occurred_at < $4)"#,
+            query.email,
+            kind,
+            query.from,
+            query.to
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+
+        let events = sqlx::query_as!(
+            AuditEventRow,
+            "select occurred_at, kind, email, ip, user_agent, request_id, detail
+            from audit_events
+            where ($1::text is null or email = $1)
+              and ($2::text is null or kind = $2)
+              and ($3::timestamptz is null or occurred_at >= $3)
+              and ($4::timestamptz is null or occurred_at < $4)
+            order by occurred_at desc, id desc
+            limit $5 offset $6",
+            query.email,
+            kind,
+            query.from,
+            query.to,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(AuditEvent::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AuditPage {
+            events,
+            total: total.try_into().unwrap_or_default(),
+        })
+    }
+}
+
+struct AuditEventRow {
+    occurred_at: DateTime<Utc>,
+    kind: String,
+    email: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+    detail: Option<String>,
+}
+
+impl TryFrom<AuditEventRow> for AuditEvent {
+    type Error = AuditSinkError;
+
+    fn try_from(row: AuditEventRow) -> Result<Self, Self::Error> {
+        Ok(AuditEvent {
+            occurred_at: row.occurred_at,
+            kind: AuditEventKind::parse(&row.kind).map_err(AuditSinkError::UnexpectedError)?,
+            email: row.email,
+            ip: row.ip,
+            user_agent: row.user_agent,
+            request_id: row.request_id,
+            detail: row.detail,
+        })
+    }
+}
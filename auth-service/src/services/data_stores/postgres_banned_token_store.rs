@@ -0,0 +1,62 @@
+use crate::utils::metrics;
+use crate::{BannedTokenStore, BannedTokenStoreError};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+    // Banned tokens only need to outlive the tokens themselves
+    token_ttl: Duration,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool, token_ttl: Duration) -> Self {
+        Self { pool, token_ttl }
+    }
+
+    // Expired rows are already ignored, this only keeps the table from growing
+    #[tracing::instrument(name = "Purging expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let _timer = metrics().time_store_operation("postgres_banned_token_store", "purge_expired");
+        let result = sqlx::query!("delete from banned_tokens where expires_at <= now()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding token in PostgreSQL", skip_all)]
+    async fn add_token(&self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = metrics().time_store_operation("postgres_banned_token_store", "add_token");
+        sqlx::query!(
+            "insert into banned_tokens (token, expires_at)
+            values ($1, now() + make_interval(secs => $2))
+            on conflict (token) do update set expires_at = excluded.expires_at",
+            token.expose_secret(),
+            self.token_ttl.as_secs_f64()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Contains token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_banned_token_store", "contains_token");
+        sqlx::query_scalar!(
+            r#"select exists(
+                select 1 from banned_tokens where token = $1 and expires_at > now()
+            ) as "exists!""#,
+            token.expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))
+    }
+}
@@ -0,0 +1,57 @@
+use crate::domain::{DeliveryEvent, Email, EmailDeliveryStore, EmailDeliveryStoreError};
+use crate::utils::metrics;
+use color_eyre::Result;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+pub struct PostgresEmailDeliveryStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailDeliveryStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailDeliveryStore for PostgresEmailDeliveryStore {
+    #[tracing::instrument(name = "Recording email delivery event in PostgreSQL", skip_all)]
+    async fn record_event(&self, event: DeliveryEvent) -> Result<(), EmailDeliveryStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_email_delivery_store", "record_event");
+        sqlx::query!(
+            "insert into email_delivery_events (recipient, kind, occurred_at, message_id, detail)
+            values ($1, $2, $3, $4, $5)",
+            event.recipient,
+            event.kind.as_str(),
+            event.occurred_at,
+            event.message_id,
+            event.detail
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailDeliveryStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking email deliverability in PostgreSQL", skip_all)]
+    async fn is_undeliverable(&self, recipient: &Email) -> Result<bool, EmailDeliveryStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_email_delivery_store", "is_undeliverable");
+        let latest = sqlx::query_scalar!(
+            "select kind from email_delivery_events
+            where recipient = lower($1) and kind <> 'soft_bounce'
+            order by occurred_at desc, id desc
+            limit 1",
+            recipient.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailDeliveryStoreError::UnexpectedError(e.into()))?;
+        Ok(matches!(
+            latest.as_deref(),
+            Some("hard_bounce" | "spam_complaint")
+        ))
+    }
+}
@@ -0,0 +1,188 @@
+use crate::domain::{
+    Email, EmailMessage, EmailOutbox, EmailOutboxError, OutboxEmail, OutboxEntry, OutboxStatus,
+};
+use crate::utils::metrics;
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub struct PostgresEmailOutbox {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for PostgresEmailOutbox {
+    #[tracing::instrument(name = "Enqueueing email in PostgreSQL", skip_all)]
+    async fn enqueue(&self, email: OutboxEmail) -> Result<bool, EmailOutboxError> {
+        let _timer = metrics().time_store_operation("postgres_email_outbox", "enqueue");
+        let result = sqlx::query!(
+            "insert into email_outbox
+                (idempotency_key, recipient, subject, html_body, text_body, expires_at)
+            values ($1, $2, $3, $4, $5, $6)
+            on conflict (idempotency_key) do nothing",
+            email.idempotency_key,
+            email.recipient.as_ref().expose_secret(),
+            email.message.subject,
+            email.message.html_body,
+            email.message.text_body,
+            email.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    // `skip locked` lets several workers claim batches concurrently without handing out the same email
+    #[tracing::instrument(name = "Claiming due emails from PostgreSQL", skip_all)]
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxError> {
+        let _timer = metrics().time_store_operation("postgres_email_outbox", "claim_due");
+        sqlx::query_as!(
+            OutboxEntryRow,
+            "with due as (
+                select id from email_outbox
+                where status = 'pending' and next_attempt_at <= now()
+                order by next_attempt_at
+                limit $1
+                for update skip locked
+            )
+            update email_outbox
+            set attempts = email_outbox.attempts + 1,
+                next_attempt_at = now() + make_interval(secs => $2)
+            from due
+            where email_outbox.id = due.id
+            returning email_outbox.id, idempotency_key, recipient, subject, html_body, text_body,
+                expires_at, status, attempts, last_error",
+            i64::from(limit),
+            lease.as_secs_f64()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(OutboxEntry::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Marking email delivered in PostgreSQL", skip_all)]
+    async fn mark_delivered(&self, id: i64) -> Result<(), EmailOutboxError> {
+        let _timer = metrics().time_store_operation("postgres_email_outbox", "mark_delivered");
+        let result = sqlx::query!(
+            "update email_outbox
+            set status = 'delivered', last_error = null, html_body = '', text_body = ''
+            where id = $1",
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxError::EntryNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError> {
+        let _timer = metrics().time_store_operation("postgres_email_outbox", "mark_failed");
+        let result = sqlx::query!(
+            "update email_outbox
+            set last_error = $2,
+                status = case when $3::timestamptz is null then 'dead' else status end,
+                html_body = case when $3::timestamptz is null then '' else html_body end,
+                text_body = case when $3::timestamptz is null then '' else text_body end,
+                next_attempt_at = coalesce($3, next_attempt_at)
+            where id = $1",
+            id,
+            error,
+            retry_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxError::EntryNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving outbox entry from PostgreSQL", skip_all)]
+    async fn get_entry(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxEntry>, EmailOutboxError> {
+        let _timer = metrics().time_store_operation("postgres_email_outbox", "get_entry");
+        sqlx::query_as!(
+            OutboxEntryRow,
+            "select id, idempotency_key, recipient, subject, html_body, text_body, expires_at,
+                status, attempts, last_error
+            from email_outbox
+            where idempotency_key = $1",
+            idempotency_key
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?
+        .map(OutboxEntry::try_from)
+        .transpose()
+    }
+}
+
+struct OutboxEntryRow {
+    id: i64,
+    idempotency_key: String,
+    recipient: String,
+    subject: String,
+    html_body: String,
+    text_body: String,
+    expires_at: Option<DateTime<Utc>>,
+    status: String,
+    attempts: i32,
+    last_error: Option<String>,
+}
+
+impl TryFrom<OutboxEntryRow> for OutboxEntry {
+    type Error = EmailOutboxError;
+
+    fn try_from(row: OutboxEntryRow) -> Result<Self, Self::Error> {
+        Ok(OutboxEntry {
+            id: row.id,
+            email: OutboxEmail {
+                idempotency_key: row.idempotency_key,
+                recipient: Email::parse(SecretString::from(row.recipient))
+                    .map_err(EmailOutboxError::UnexpectedError)?,
+                message: EmailMessage {
+                    subject: row.subject,
+                    html_body: row.html_body,
+                    text_body: row.text_body,
+                },
+                expires_at: row.expires_at,
+            },
+            status: OutboxStatus::parse(&row.status).map_err(EmailOutboxError::UnexpectedError)?,
+            attempts: row
+                .attempts
+                .try_into()
+                .map_err(|e: std::num::TryFromIntError| {
+                    EmailOutboxError::UnexpectedError(e.into())
+                })?,
+            last_error: row.last_error,
+        })
+    }
+}
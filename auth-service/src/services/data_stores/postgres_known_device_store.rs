@@ -0,0 +1,67 @@
+use crate::domain::{Device, DeviceSighting, Email, KnownDeviceStore, KnownDeviceStoreError};
+use crate::utils::metrics;
+use color_eyre::Result;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+pub struct PostgresKnownDeviceStore {
+    pool: PgPool,
+}
+
+impl PostgresKnownDeviceStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for PostgresKnownDeviceStore {
+    #[tracing::instrument(name = "Remembering device in PostgreSQL", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: Device,
+    ) -> Result<DeviceSighting, KnownDeviceStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_known_device_store", "remember_device");
+        let email = email.as_ref().expose_secret();
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let has_devices = sqlx::query_scalar!(
+            r#"select exists(select 1 from known_devices where email = $1) as "exists!""#,
+            email
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        // `xmax` is zero only for freshly inserted rows, telling a new device apart from a known one
+        let inserted = sqlx::query_scalar!(
+            r#"insert into known_devices (email, ip, user_agent)
+            values ($1, $2, $3)
+            on conflict (email, ip, user_agent) do update set last_seen_at = now()
+            returning (xmax = 0) as "inserted!""#,
+            email,
+            device.ip,
+            device.user_agent
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(match (inserted, has_devices) {
+            (false, _) => DeviceSighting::Known,
+            (true, false) => DeviceSighting::First,
+            (true, true) => DeviceSighting::New,
+        })
+    }
+}
@@ -0,0 +1,121 @@
+use crate::domain::{
+    Email, LoginAttemptId, PhoneNumber, PhoneVerification, PhoneVerificationStore,
+    PhoneVerificationStoreError, TwoFACode,
+};
+use crate::utils::metrics;
+use color_eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub struct PostgresPhoneVerificationStore {
+    pool: PgPool,
+    code_ttl: Duration,
+    max_sends: u32,
+    send_window: Duration,
+}
+
+impl PostgresPhoneVerificationStore {
+    pub fn new(pool: PgPool, code_ttl: Duration, max_sends: u32, send_window: Duration) -> Self {
+        Self {
+            pool,
+            code_ttl,
+            max_sends,
+            send_window,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for PostgresPhoneVerificationStore {
+    // The send limit is checked and counted in the same statement, so concurrent requests
+    // can't get past it
+    #[tracing::instrument(name = "Adding phone verification code in PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        verification: PhoneVerification,
+    ) -> Result<(), PhoneVerificationStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_phone_verification_store", "add_code");
+        let result = sqlx::query!(
+            "insert into phone_verifications
+                (email, phone_number, login_attempt_id, code, expires_at, window_started_at, sends)
+            values ($1, $2, $3, $4, now() + make_interval(secs => $5), now(), 1)
+            on conflict (email) do update
+            set phone_number = excluded.phone_number,
+                login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                expires_at = excluded.expires_at,
+                window_started_at = case
+                    when phone_verifications.window_started_at <= now() - make_interval(secs => $6)
+                    then now() else phone_verifications.window_started_at end,
+                sends = case
+                    when phone_verifications.window_started_at <= now() - make_interval(secs => $6)
+                    then 1 else phone_verifications.sends + 1 end
+            where phone_verifications.window_started_at <= now() - make_interval(secs => $6)
+                or phone_verifications.sends < $7",
+            email.as_ref().expose_secret(),
+            verification.phone_number.as_ref().expose_secret(),
+            verification.login_attempt_id.as_ref().expose_secret(),
+            verification.code.as_ref().expose_secret(),
+            self.code_ttl.as_secs_f64(),
+            self.send_window.as_secs_f64(),
+            self.max_sends as i32
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PhoneVerificationStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(PhoneVerificationStoreError::TooManyCodesSent);
+        }
+        Ok(())
+    }
+
+    // Only the caller that expires a live code gets `Ok`, so a code is consumed once.
+    // The row stays behind to keep counting towards the send limit.
+    #[tracing::instrument(name = "Remove phone verification code in PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), PhoneVerificationStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_phone_verification_store", "remove_code");
+        let result = sqlx::query!(
+            "update phone_verifications set expires_at = now()
+            where email = $1 and expires_at > now()",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PhoneVerificationStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(PhoneVerificationStoreError::VerificationNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting phone verification code in PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<PhoneVerification, PhoneVerificationStoreError> {
+        let _timer =
+            metrics().time_store_operation("postgres_phone_verification_store", "get_code");
+        let row = sqlx::query!(
+            "select phone_number, login_attempt_id, code from phone_verifications
+            where email = $1 and expires_at > now()",
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PhoneVerificationStoreError::UnexpectedError(e.into()))?
+        .ok_or(PhoneVerificationStoreError::VerificationNotFound)?;
+
+        Ok(PhoneVerification {
+            phone_number: PhoneNumber::parse(SecretString::from(row.phone_number))
+                .map_err(PhoneVerificationStoreError::UnexpectedError)?,
+            login_attempt_id: LoginAttemptId::parse(SecretString::from(row.login_attempt_id))
+                .map_err(PhoneVerificationStoreError::UnexpectedError)?,
+            code: TwoFACode::parse(SecretString::from(row.code))
+                .map_err(PhoneVerificationStoreError::UnexpectedError)?,
+        })
+    }
+}
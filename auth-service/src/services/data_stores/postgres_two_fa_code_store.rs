@@ -0,0 +1,98 @@
+use crate::utils::metrics;
+use crate::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use color_eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+    code_ttl: Duration,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool, code_ttl: Duration) -> Self {
+        Self { pool, code_ttl }
+    }
+
+    // Expired rows are already ignored, this only keeps the table from growing
+    #[tracing::instrument(name = "Purging expired 2FA codes from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("postgres_two_fa_code_store", "purge_expired");
+        let result = sqlx::query!("delete from two_fa_codes where expires_at <= now()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    // A new code replaces the previous one for the same email, like the Redis key does
+    #[tracing::instrument(name = "Adding 2FA Code in PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("postgres_two_fa_code_store", "add_code");
+        sqlx::query!(
+            "insert into two_fa_codes (email, login_attempt_id, code, expires_at)
+            values ($1, $2, $3, now() + make_interval(secs => $4))
+            on conflict (email) do update
+            set login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                expires_at = excluded.expires_at",
+            email.as_ref().expose_secret(),
+            login_attempt_id.as_ref().expose_secret(),
+            code.as_ref().expose_secret(),
+            self.code_ttl.as_secs_f64()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    // Only the caller that deletes a live code gets `Ok`, so a code is consumed once
+    #[tracing::instrument(name = "Remove 2FA Code in PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("postgres_two_fa_code_store", "remove_code");
+        let result = sqlx::query!(
+            "delete from two_fa_codes where email = $1 and expires_at > now()",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting 2FA Code in PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("postgres_two_fa_code_store", "get_code");
+        let row = sqlx::query!(
+            "select login_attempt_id, code from two_fa_codes
+            where email = $1 and expires_at > now()",
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id = LoginAttemptId::parse(SecretString::from(row.login_attempt_id))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(SecretString::from(row.code))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        Ok((login_attempt_id, code))
+    }
+}
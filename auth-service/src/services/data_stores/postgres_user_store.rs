@@ -1,6 +1,11 @@
-use crate::{Email, Password, User, UserStore, UserStoreError};
+use crate::utils::metrics;
+use crate::{
+    AccountStatus, Email, Password, PhoneNumber, TwoFAChannel, User, UserPage, UserQuery,
+    UserStore, UserStoreError,
+};
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use chrono::{DateTime, Utc};
 use color_eyre::Result;
 use color_eyre::eyre::eyre;
 use secrecy::{ExposeSecret, SecretString};
@@ -19,7 +24,8 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     // Implement all required methods. Note that you will need to make SQL queries against our PostgreSQL instance inside these methods.
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "add_user");
         let hashed_password = compute_password_hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
@@ -34,14 +40,25 @@ impl UserStore for PostgresUserStore {
             .map_err(|_| UserStoreError::UnexpectedError)?;
         */
         let result = sqlx::query!(
-            "insert into users (email,password_hash,requires_2fa) values ($1,$2,$3)",
+            "insert into users (email,password_hash,requires_2fa,is_admin,status,phone_number,phone_verified,two_fa_channel)
+            values ($1,$2,$3,$4,$5,$6,$7,$8)",
             user.email.as_ref().expose_secret(),
             &hashed_password.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.is_admin,
+            user.status.as_str(),
+            user.phone_number
+                .as_ref()
+                .map(|phone_number| phone_number.as_ref().expose_secret()),
+            user.phone_verified,
+            user.two_fa_channel.as_str()
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .map_err(|e| match e.as_database_error() {
+            Some(db_error) if db_error.is_unique_violation() => UserStoreError::UserAlreadyExists,
+            _ => UserStoreError::UnexpectedError(e.into()),
+        })?;
         if result.rows_affected() == 0 {
             return Err(UserStoreError::UserAlreadyExists);
         }
@@ -50,6 +67,7 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "get_user");
         /*
         let user = sqlx::query_as::<_, User>(
             "select
@@ -62,22 +80,17 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|_| UserStoreError::UnexpectedError)?;
         */
-        sqlx::query!(
-            "select email, password_hash, requires_2fa from users where email = $1",
+        sqlx::query_as!(
+            UserRow,
+            "select email, password_hash, requires_2fa, is_admin, status, tokens_revoked_at,
+                token_version, phone_number, phone_verified, two_fa_channel
+            from users where email = $1",
             email.as_ref().expose_secret()
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|user_row| {
-            Ok(User {
-                email: Email::parse(SecretString::from(user_row.email))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(SecretString::from(user_row.password_hash))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: user_row.requires_2fa,
-            })
-        })
+        .map(User::try_from)
         .ok_or(UserStoreError::UserNotFound)?
     }
 
@@ -87,6 +100,7 @@ impl UserStore for PostgresUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "validate_user");
         /*
         let user = sqlx::query("select email,password_hash from users where email = $1")
                     .bind(email.as_ref())
@@ -112,6 +126,237 @@ impl UserStore for PostgresUserStore {
             .map_err(|_| UserStoreError::InvalidCredentials),
         }
     }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "list_users");
+        let pattern = query
+            .search
+            .as_ref()
+            .map(|search| format!("%{}%", escape_like(search)));
+        let limit =
+            i64::try_from(query.limit).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let offset =
+            i64::try_from(query.offset).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let total = sqlx::query_scalar!(
+            r#"select count(*) as "count!" from users where ($1::text is null or email ilike $1)"#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let users = sqlx::query_as!(
+            UserRow,
+            "select email, password_hash, requires_2fa, is_admin, status, tokens_revoked_at,
+                token_version, phone_number, phone_verified, two_fa_channel
+            from users where ($1::text is null or email ilike $1)
+            order by email limit $2 offset $3",
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total.try_into().unwrap_or_default(),
+        })
+    }
+
+    #[tracing::instrument(name = "Setting user status in PostgreSQL", skip_all)]
+    async fn set_status(&self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "set_status");
+        let result = sqlx::query!(
+            "update users set status = $2 where email = $1",
+            email.as_ref().expose_secret(),
+            status.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "update_password");
+        let hashed_password = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+        let result = sqlx::query!(
+            "update users set password_hash = $2 where email = $1",
+            email.as_ref().expose_secret(),
+            hashed_password.expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting user 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "set_requires_2fa");
+        let result = sqlx::query!(
+            "update users set requires_2fa = $2 where email = $1",
+            email.as_ref().expose_secret(),
+            requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking user tokens in PostgreSQL", skip_all)]
+    async fn revoke_tokens(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "revoke_tokens");
+        let result = sqlx::query!(
+            "update users set tokens_revoked_at = $2, token_version = token_version + 1
+            where email = $1",
+            email.as_ref().expose_secret(),
+            Utc::now()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting user phone number in PostgreSQL", skip_all)]
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "set_phone_number");
+        let result = sqlx::query!(
+            "update users set phone_number = $2, phone_verified = false, two_fa_channel = 'email'
+            where email = $1",
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user phone number verified in PostgreSQL", skip_all)]
+    async fn mark_phone_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "mark_phone_verified");
+        let result = sqlx::query!(
+            "update users set phone_verified = phone_number is not null where email = $1",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting user 2FA channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let _timer = metrics().time_store_operation("postgres_user_store", "set_two_fa_channel");
+        let result = sqlx::query!(
+            "update users set two_fa_channel = $2 where email = $1",
+            email.as_ref().expose_secret(),
+            channel.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+}
+
+// Row shape shared by every query that loads a full user
+struct UserRow {
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    is_admin: bool,
+    status: String,
+    tokens_revoked_at: Option<DateTime<Utc>>,
+    token_version: i32,
+    phone_number: Option<String>,
+    phone_verified: bool,
+    two_fa_channel: String,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            email: Email::parse(SecretString::from(row.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(SecretString::from(row.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            is_admin: row.is_admin,
+            status: AccountStatus::parse(&row.status).map_err(UserStoreError::UnexpectedError)?,
+            tokens_revoked_at: row.tokens_revoked_at,
+            token_version: u32::try_from(row.token_version)
+                .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+            phone_number: row
+                .phone_number
+                .map(|phone_number| PhoneNumber::parse(SecretString::from(phone_number)))
+                .transpose()
+                .map_err(UserStoreError::UnexpectedError)?,
+            phone_verified: row.phone_verified,
+            two_fa_channel: TwoFAChannel::parse(&row.two_fa_channel)
+                .map_err(UserStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+// Escape LIKE wildcards so a search term is matched literally
+fn escape_like(search: &str) -> String {
+    search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 // Helper function to verify if a given password matches an expected hash
@@ -129,6 +374,7 @@ async fn verify_password_hash(
     // The span represents the execution context for the compute_password_hash function.
     let current_span: tracing::Span = tracing::Span::current();
     tokio::task::spawn_blocking(move || {
+        let _timer = metrics().time_password_hash("verify");
         // This code block ensures that the operations within the closure are executed within the context of the current span.
         // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
         current_span.in_scope(|| {
@@ -154,6 +400,7 @@ async fn verify_password_hash(
 async fn compute_password_hash(password: SecretString) -> Result<SecretString> {
     let current_span: tracing::Span = tracing::Span::current();
     tokio::task::spawn_blocking(move || {
+        let _timer = metrics().time_password_hash("hash");
         current_span.in_scope(|| {
             let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
             let password_hash = Argon2::new(
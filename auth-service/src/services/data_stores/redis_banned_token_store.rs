@@ -1,41 +1,40 @@
-use crate::utils::TOKEN_TTL_SECONDS;
+use crate::RedisConnection;
+use crate::utils::metrics;
 use crate::{BannedTokenStore, BannedTokenStoreError};
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::AsyncCommands;
 use secrecy::{ExposeSecret, SecretString};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    // Banned tokens only need to outlive the tokens themselves
+    token_ttl: Duration,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection, token_ttl: Duration) -> Self {
+        Self { conn, token_ttl }
     }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Adding token in Redis", skip_all)]
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
+    async fn add_token(&self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = metrics().time_store_operation("redis_banned_token_store", "add_token");
         // 1. Create a new key using the get_key helper function.
         // 2. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
         // The value should simply be a `true` (boolean value).
-        // The expiration time should be set to TOKEN_TTL_SECONDS.
-        // NOTE: The TTL is expected to be a u64 so you will have to cast TOKEN_TTL_SECONDS to a u64.
-        // Return BannedTokenStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        // The expiration time should be set to the token TTL.
+        // Return BannedTokenStoreError::UnexpectedError if the call to set_ex fails.
+        let ttl = self.token_ttl.as_secs();
         let key = get_key(token.expose_secret());
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .set_ex(&key, true, ttl)
+            .await
             .wrap_err("failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
         Ok(())
@@ -43,13 +42,14 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
     #[tracing::instrument(name = "Contains token", skip_all)]
     async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer = metrics().time_store_operation("redis_banned_token_store", "contains_token");
         // Check if the token exists by calling the exists method on the Redis connection
         let key = get_key(token.expose_secret());
         let is_banned = self
             .conn
-            .write()
-            .await
+            .clone()
             .exists(key)
+            .await
             .wrap_err("fail to check if token exists in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
         Ok(is_banned)
@@ -57,6 +57,8 @@ impl BannedTokenStore for RedisBannedTokenStore {
 }
 
 // We are using a key prefix to prevent collisions and organize data!
+// Keys hold no hash tag, so in a cluster tokens spread over every shard by the whole key.
+// Keep the format as is: tokens banned before a change would otherwise be accepted again.
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
 fn get_key(token: &str) -> String {
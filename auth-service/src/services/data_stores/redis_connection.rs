@@ -0,0 +1,196 @@
+use crate::get_redis_client;
+use crate::settings::RedisSettings;
+use redis::aio::{
+    ConnectionLike, ConnectionManager, ConnectionManagerConfig, MultiplexedConnection,
+};
+use redis::cluster::ClusterClientBuilder;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType};
+use redis::{
+    AsyncConnectionConfig, Cmd, ConnectionAddr, ErrorKind, IntoConnectionInfo, Pipeline,
+    RedisError, RedisFuture, RedisResult, TlsMode, Value,
+};
+use secrecy::ExposeSecret;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+// The connection shared by the Redis stores, whichever way Redis is deployed. Clones share
+// the underlying connections, and every command touches a single key so it can be routed to
+// any cluster shard.
+#[derive(Clone)]
+pub enum RedisConnection {
+    // Reconnects to the same server when the connection drops
+    Standalone(ConnectionManager),
+    // Asks Sentinel for the current master when the connection drops or the master it is
+    // connected to has been demoted after a failover
+    Sentinel(SentinelConnection),
+    // Routes each key to the shard owning its hash slot, following the cluster as slots move
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    pub async fn connect(settings: &RedisSettings) -> RedisResult<Self> {
+        match settings.mode.as_str() {
+            "sentinel" => Ok(Self::Sentinel(SentinelConnection::connect(settings).await?)),
+            "cluster" => {
+                let nodes: Vec<_> = settings
+                    .cluster
+                    .urls
+                    .iter()
+                    .map(|url| url.expose_secret())
+                    .collect();
+                let client = ClusterClientBuilder::new(nodes)
+                    .connection_timeout(settings.connection_timeout)
+                    .response_timeout(settings.response_timeout)
+                    .build()?;
+                Ok(Self::Cluster(client.get_async_connection().await?))
+            }
+            _ => {
+                let client = get_redis_client(&settings.url())?;
+                let config = ConnectionManagerConfig::new()
+                    .set_connection_timeout(settings.connection_timeout)
+                    .set_response_timeout(settings.response_timeout);
+                Ok(Self::Standalone(
+                    ConnectionManager::new_with_config(client, config).await?,
+                ))
+            }
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Standalone(conn) => conn.req_packed_command(cmd),
+            Self::Sentinel(conn) => conn.req_packed_command(cmd),
+            Self::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Standalone(conn) => conn.req_packed_commands(cmd, offset, count),
+            Self::Sentinel(conn) => conn.req_packed_commands(cmd, offset, count),
+            Self::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Standalone(conn) => conn.get_db(),
+            Self::Sentinel(conn) => conn.get_db(),
+            Self::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SentinelConnection {
+    sentinel: Arc<Mutex<SentinelClient>>,
+    config: AsyncConnectionConfig,
+    master: Arc<RwLock<MultiplexedConnection>>,
+    db: i64,
+}
+
+impl SentinelConnection {
+    async fn connect(settings: &RedisSettings) -> RedisResult<Self> {
+        let master = settings.url().expose_secret().into_connection_info()?;
+        let tls_mode = match master.addr {
+            ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+            ConnectionAddr::TcpTls { .. } => Some(TlsMode::Secure),
+            _ => None,
+        };
+        let db = master.redis.db;
+        let node_connection_info = SentinelNodeConnectionInfo {
+            tls_mode,
+            redis_connection_info: Some(master.redis),
+        };
+        let sentinels: Vec<_> = settings
+            .sentinel
+            .urls
+            .iter()
+            .map(|url| url.expose_secret())
+            .collect();
+        let mut sentinel = SentinelClient::build(
+            sentinels,
+            settings.sentinel.master_name.clone(),
+            Some(node_connection_info),
+            SentinelServerType::Master,
+        )?;
+        let config = AsyncConnectionConfig::new()
+            .set_connection_timeout(settings.connection_timeout)
+            .set_response_timeout(settings.response_timeout);
+        let master = sentinel.get_async_connection_with_config(&config).await?;
+        Ok(Self {
+            sentinel: Arc::new(Mutex::new(sentinel)),
+            config,
+            master: Arc::new(RwLock::new(master)),
+            db,
+        })
+    }
+
+    fn master(&self) -> MultiplexedConnection {
+        self.master.read().expect("lock poisoned").clone()
+    }
+
+    // Replaces the master connection in the background, so the failed command returns
+    // straight away and later ones go to the new master. Concurrent failures trigger a
+    // single lookup.
+    fn reconnect(&self) {
+        let Ok(mut sentinel) = self.sentinel.clone().try_lock_owned() else {
+            return;
+        };
+        let config = self.config.clone();
+        let master = self.master.clone();
+        tokio::spawn(async move {
+            match sentinel.get_async_connection_with_config(&config).await {
+                Ok(conn) => *master.write().expect("lock poisoned") = conn,
+                Err(e) => tracing::warn!(error = %e, "failed to reconnect to the Redis master"),
+            }
+        });
+    }
+
+    fn needs_new_master(error: &RedisError) -> bool {
+        error.is_unrecoverable_error() || error.kind() == ErrorKind::ReadOnly
+    }
+}
+
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let result = self.master().req_packed_command(cmd).await;
+            if let Err(e) = &result
+                && Self::needs_new_master(e)
+            {
+                self.reconnect();
+            }
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let result = self.master().req_packed_commands(cmd, offset, count).await;
+            if let Err(e) = &result
+                && Self::needs_new_master(e)
+            {
+                self.reconnect();
+            }
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.db
+    }
+}
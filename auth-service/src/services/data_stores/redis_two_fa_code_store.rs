@@ -1,18 +1,20 @@
+use crate::RedisConnection;
+use crate::utils::metrics;
 use crate::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
 use color_eyre::Result;
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::AsyncCommands;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    code_ttl: Duration,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection, code_ttl: Duration) -> Self {
+        Self { conn, code_ttl }
     }
 }
 
@@ -20,18 +22,19 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Adding 2FA Code in Redis", skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("redis_two_fa_code_store", "add_code");
         // 1. Create a new key using the get_key helper function.
         // 2. Create a TwoFATuple instance.
         // 3. Use serde_json::to_string to serialize the TwoFATuple instance into a JSON string.
         // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
         // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
         // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
+        // The expiration time should be set to the 2FA code TTL.
         // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
         let key = get_key(&email);
         let two_fa_tuple = (
@@ -43,27 +46,32 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
         let _: () = self
             .conn
-            .write()
+            .clone()
+            .set_ex(key, json_str, self.code_ttl.as_secs())
             .await
-            .set_ex(key, json_str, TEN_MINUTES_IN_SECONDS)
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
         Ok(())
     }
 
     #[tracing::instrument(name = "Remove 2FA Code in Redis", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("redis_two_fa_code_store", "remove_code");
         // 1. Create a new key using the get_key helper function.
         // 2. Call the del command on the Redis connection to delete the 2FA code entry.
-        // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
+        // Return TwoFACodeStoreError::UnexpectedError if the operation fails, and
+        // TwoFACodeStoreError::LoginAttemptIdNotFound if there was no code to remove.
         let key = get_key(email);
-        let _: () = self
+        let removed: u64 = self
             .conn
-            .write()
-            .await
+            .clone()
             .del(key)
+            .await
             .wrap_err("failed to remove code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        if removed == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
         Ok(())
     }
 
@@ -72,15 +80,24 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let _timer = metrics().time_store_operation("redis_two_fa_code_store", "get_code");
         // 1. Create a new key using the get_key helper function.
         // 2. Call the get command on the Redis connection to get the value stored for the key.
-        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if the operation fails.
+        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if there is no code, and
+        // TwoFACodeStoreError::UnexpectedError if Redis can't be reached.
         // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFATuple.
         // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
         // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
         let key = get_key(email);
-        match self.conn.write().await.get::<_, String>(&key) {
-            Ok(value) => {
+        let value: Option<String> = self
+            .conn
+            .clone()
+            .get(&key)
+            .await
+            .wrap_err("failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        match value {
+            Some(value) => {
                 let data: TwoFATuple = serde_json::from_str(&value)
                     .wrap_err("failed to deserialize 2FA tuple")
                     .map_err(TwoFACodeStoreError::UnexpectedError)?;
@@ -91,7 +108,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
                 Ok((login_attempt_id, email_code))
             }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 }
@@ -99,7 +116,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
+// Like banned tokens, codes are one key per email so each command goes to a single shard
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
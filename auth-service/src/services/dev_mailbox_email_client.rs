@@ -0,0 +1,189 @@
+use crate::domain::{Email, EmailClient, EmailMessage};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use color_eyre::eyre::Context;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+// An email captured by the dev mailbox instead of being delivered
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CapturedEmail {
+    // UUID v7, so ids sort in the order messages were captured
+    pub id: Uuid,
+    pub recipient: String,
+    #[serde(rename = "sentAt")]
+    pub sent_at: DateTime<Utc>,
+    pub subject: String,
+    #[serde(rename = "htmlBody")]
+    pub html_body: String,
+    #[serde(rename = "textBody")]
+    pub text_body: String,
+}
+
+pub enum DevMailboxStorage {
+    Memory(RwLock<Vec<CapturedEmail>>),
+    // One `<id>.json` file per message, so captured mail survives restarts
+    Directory(PathBuf),
+}
+
+// Email client for local development. Nothing leaves the machine: every message is logged
+// to stdout and kept so `GET /dev/mailbox` can show it.
+pub struct DevMailboxEmailClient {
+    storage: DevMailboxStorage,
+}
+
+impl DevMailboxEmailClient {
+    pub fn in_memory() -> Self {
+        Self {
+            storage: DevMailboxStorage::Memory(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn in_directory(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            storage: DevMailboxStorage::Directory(directory.into()),
+        }
+    }
+
+    // Newest first
+    pub async fn list(&self) -> Result<Vec<CapturedEmail>> {
+        let mut emails = match &self.storage {
+            DevMailboxStorage::Memory(emails) => emails.read().await.clone(),
+            DevMailboxStorage::Directory(directory) => {
+                let mut emails = Vec::new();
+                let mut entries = match tokio::fs::read_dir(directory).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(emails),
+                    Err(e) => return Err(e.into()),
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if path
+                        .extension()
+                        .is_some_and(|extension| extension == "json")
+                    {
+                        let contents = tokio::fs::read(&path).await?;
+                        let email = serde_json::from_slice(&contents)
+                            .wrap_err_with(|| format!("Invalid mailbox file {}", path.display()))?;
+                        emails.push(email);
+                    }
+                }
+                emails
+            }
+        };
+        emails.sort_by_key(|email| std::cmp::Reverse(email.id));
+        Ok(emails)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<CapturedEmail>> {
+        match &self.storage {
+            DevMailboxStorage::Memory(emails) => Ok(emails
+                .read()
+                .await
+                .iter()
+                .find(|email| email.id == id)
+                .cloned()),
+            DevMailboxStorage::Directory(directory) => {
+                // The id is a parsed UUID, so it can't escape the mailbox directory
+                match tokio::fs::read(directory.join(format!("{id}.json"))).await {
+                    Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for DevMailboxEmailClient {
+    #[tracing::instrument(name = "Capturing email in dev mailbox", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = CapturedEmail {
+            id: Uuid::now_v7(),
+            recipient: recipient.as_ref().expose_secret().to_owned(),
+            sent_at: Utc::now(),
+            subject: message.subject.clone(),
+            html_body: message.html_body.clone(),
+            text_body: message.text_body.clone(),
+        };
+        tracing::info!(
+            "Captured email {} to {} with subject: {}\n{}",
+            email.id,
+            email.recipient,
+            email.subject,
+            email.text_body
+        );
+
+        match &self.storage {
+            DevMailboxStorage::Memory(emails) => emails.write().await.push(email),
+            DevMailboxStorage::Directory(directory) => {
+                tokio::fs::create_dir_all(directory).await?;
+                let path = directory.join(format!("{}.json", email.id));
+                tokio::fs::write(&path, serde_json::to_vec_pretty(&email)?)
+                    .await
+                    .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: format!("<p>{subject}</p>"),
+            text_body: subject.to_owned(),
+        }
+    }
+
+    async fn assert_captures_newest_first(mailbox: DevMailboxEmailClient) {
+        let recipient = Email::parse(SecretString::from("test@example.com")).unwrap();
+        mailbox
+            .send_email(&recipient, &message("first"))
+            .await
+            .unwrap();
+        mailbox
+            .send_email(&recipient, &message("second"))
+            .await
+            .unwrap();
+
+        let emails = mailbox.list().await.unwrap();
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].subject, "second");
+        assert_eq!(emails[1].recipient, "test@example.com");
+        assert_eq!(
+            mailbox.get(emails[1].id).await.unwrap(),
+            Some(emails[1].clone())
+        );
+        assert_eq!(mailbox.get(Uuid::now_v7()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn should_capture_emails_in_memory() {
+        assert_captures_newest_first(DevMailboxEmailClient::in_memory()).await;
+    }
+
+    #[tokio::test]
+    async fn should_capture_emails_in_directory() {
+        let directory = std::env::temp_dir().join(format!("dev-mailbox-{}", Uuid::now_v7()));
+        assert_eq!(
+            DevMailboxEmailClient::in_directory(&directory)
+                .list()
+                .await
+                .unwrap(),
+            vec![]
+        );
+
+        assert_captures_newest_first(DevMailboxEmailClient::in_directory(&directory)).await;
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use crate::domain::{EmailDeliveryError, EmailOutboxError, RetryPolicy};
+use crate::utils::metrics;
+use crate::{EmailClientType, EmailOutboxType};
+use chrono::Utc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Delivers queued emails in the background, retrying failures with exponential backoff
+// and dead-lettering an email once its attempts are used up or it has expired.
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxType,
+    email_client: EmailClientType,
+    policy: RetryPolicy,
+    poll_interval: Duration,
+    batch_size: u32,
+    lease: Duration,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        outbox: EmailOutboxType,
+        email_client: EmailClientType,
+        policy: RetryPolicy,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            outbox,
+            email_client,
+            policy,
+            poll_interval,
+            batch_size: 20,
+            // Long enough for a batch to be sent before another worker may pick it up again
+            lease: Duration::from_secs(300),
+        }
+    }
+
+    // Polls until shutdown, always finishing the batch in progress first
+    pub async fn run(self, shutdown: CancellationToken) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                tracing::error!(error = ?e, "failed to process email outbox");
+            }
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+        tracing::info!("email outbox worker stopped");
+    }
+
+    // Attempts every due email once and returns how many were claimed
+    #[tracing::instrument(name = "Processing email outbox", skip_all)]
+    pub async fn run_once(&self) -> Result<usize, EmailOutboxError> {
+        let entries = self.outbox.claim_due(self.batch_size, self.lease).await?;
+        for entry in &entries {
+            let expires_at = entry.email.expires_at;
+            if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+                tracing::warn!(
+                    idempotency_key = %entry.email.idempotency_key,
+                    "dropping email that expired before it could be delivered"
+                );
+                self.outbox
+                    .mark_failed(entry.id, "expired before delivery", None)
+                    .await?;
+                continue;
+            }
+            match self
+                .email_client
+                .send_email(&entry.email.recipient, &entry.email.message)
+                .await
+            {
+                Ok(()) => self.outbox.mark_delivered(entry.id).await?,
+                Err(e) => {
+                    metrics()
+                        .record_email_send_failure("outbox", EmailDeliveryError::is_permanent(&e));
+                    // Permanent failures are dead-lettered straight away
+                    let retry_at = Some(entry.attempts)
+                        .filter(|_| !EmailDeliveryError::is_permanent(&e))
+                        .and_then(|attempts| self.policy.next_delay(attempts))
+                        .and_then(|delay| chrono::Duration::from_std(delay).ok())
+                        .map(|delay| Utc::now() + delay)
+                        .filter(|retry_at| {
+                            expires_at.is_none_or(|expires_at| *retry_at < expires_at)
+                        });
+                    if retry_at.is_none() {
+                        tracing::error!(
+                            error = ?e,
+                            idempotency_key = %entry.email.idempotency_key,
+                            "giving up on email after {} attempts",
+                            entry.attempts
+                        );
+                    }
+                    self.outbox
+                        .mark_failed(entry.id, &format!("{e:#}"), retry_at)
+                        .await?;
+                }
+            }
+        }
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, EmailClient, EmailMessage, EmailOutbox, OutboxEmail, OutboxStatus};
+    use crate::services::HashMapEmailOutbox;
+    use chrono::DateTime;
+    use color_eyre::eyre::{Result, eyre};
+    use secrecy::SecretString;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Fails the first `failures` sends, then succeeds
+    struct FlakyEmailClient {
+        failures: usize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(eyre!("provider unavailable"));
+            }
+            Ok(())
+        }
+    }
+
+    fn worker(outbox: Arc<HashMapEmailOutbox>, failures: usize) -> EmailOutboxWorker {
+        let email_client = Arc::new(FlakyEmailClient {
+            failures,
+            calls: AtomicUsize::new(0),
+        });
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+        EmailOutboxWorker::new(outbox, email_client, policy, Duration::ZERO)
+    }
+
+    async fn enqueue(outbox: &HashMapEmailOutbox) {
+        enqueue_expiring(outbox, None).await;
+    }
+
+    async fn enqueue_expiring(outbox: &HashMapEmailOutbox, expires_at: Option<DateTime<Utc>>) {
+        let email = OutboxEmail {
+            idempotency_key: "key".to_owned(),
+            recipient: Email::parse(SecretString::from("test@example.com")).unwrap(),
+            message: EmailMessage {
+                subject: "subject".to_owned(),
+                html_body: "<p>body</p>".to_owned(),
+                text_body: "body".to_owned(),
+            },
+            expires_at,
+        };
+        outbox.enqueue(email).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_retry_until_delivered() {
+        let outbox = Arc::new(HashMapEmailOutbox::default());
+        enqueue(&outbox).await;
+        let worker = worker(outbox.clone(), 2);
+
+        for _ in 0..3 {
+            assert_eq!(worker.run_once().await, Ok(1));
+        }
+
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Delivered);
+        assert_eq!(entry.attempts, 3);
+        assert_eq!(worker.run_once().await, Ok(0));
+    }
+
+    struct RejectingEmailClient;
+
+    #[async_trait::async_trait]
+    impl EmailClient for RejectingEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            Err(EmailDeliveryError::Permanent(eyre!("invalid recipient")).into())
+        }
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_permanent_failures_immediately() {
+        let outbox = Arc::new(HashMapEmailOutbox::default());
+        enqueue(&outbox).await;
+        let worker = EmailOutboxWorker::new(
+            outbox.clone(),
+            Arc::new(RejectingEmailClient),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::ZERO,
+            },
+            Duration::ZERO,
+        );
+
+        assert_eq!(worker.run_once().await, Ok(1));
+
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Dead);
+        assert_eq!(entry.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_after_max_attempts() {
+        let outbox = Arc::new(HashMapEmailOutbox::default());
+        enqueue(&outbox).await;
+        let worker = worker(outbox.clone(), usize::MAX);
+
+        for _ in 0..3 {
+            assert_eq!(worker.run_once().await, Ok(1));
+        }
+
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Dead);
+        assert_eq!(entry.last_error.as_deref(), Some("provider unavailable"));
+        assert_eq!(worker.run_once().await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn should_drop_expired_emails_without_sending() {
+        let outbox = Arc::new(HashMapEmailOutbox::default());
+        enqueue_expiring(&outbox, Some(Utc::now() - chrono::Duration::seconds(1))).await;
+        let email_client = Arc::new(FlakyEmailClient {
+            failures: 0,
+            calls: AtomicUsize::new(0),
+        });
+        let worker = EmailOutboxWorker::new(
+            outbox.clone(),
+            email_client.clone(),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::ZERO,
+            },
+            Duration::ZERO,
+        );
+
+        assert_eq!(worker.run_once().await, Ok(1));
+
+        assert_eq!(email_client.calls.load(Ordering::SeqCst), 0);
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Dead);
+        assert_eq!(entry.last_error.as_deref(), Some("expired before delivery"));
+        assert!(entry.email.message.text_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_when_retry_would_be_after_expiry() {
+        let outbox = Arc::new(HashMapEmailOutbox::default());
+        enqueue_expiring(&outbox, Some(Utc::now() + chrono::Duration::minutes(1))).await;
+        let worker = EmailOutboxWorker::new(
+            outbox.clone(),
+            Arc::new(FlakyEmailClient {
+                failures: usize::MAX,
+                calls: AtomicUsize::new(0),
+            }),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_secs(300),
+                max_delay: Duration::from_secs(300),
+            },
+            Duration::ZERO,
+        );
+
+        assert_eq!(worker.run_once().await, Ok(1));
+
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Dead);
+        assert_eq!(entry.attempts, 1);
+    }
+}
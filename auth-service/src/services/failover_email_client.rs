@@ -0,0 +1,297 @@
+use crate::EmailClientType;
+use crate::domain::{Email, EmailClient, EmailDeliveryError, EmailMessage};
+use color_eyre::eyre::{Result, eyre};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Tries several email providers in priority order. A provider that keeps failing is skipped
+// by its circuit breaker until a cooldown has passed, after which one trial email is let through
+// per cooldown.
+pub struct FailoverEmailClient {
+    providers: Vec<Provider>,
+}
+
+struct Provider {
+    name: String,
+    client: EmailClientType,
+    breaker: CircuitBreaker,
+}
+
+impl FailoverEmailClient {
+    // `providers` are (name, client) pairs, highest priority first
+    pub fn new(
+        providers: Vec<(String, EmailClientType)>,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            providers: providers
+                .into_iter()
+                .map(|(name, client)| Provider {
+                    name,
+                    client,
+                    breaker: CircuitBreaker::new(failure_threshold, cooldown),
+                })
+                .collect(),
+        }
+    }
+
+    // Sends the email and returns the name of the provider that delivered it
+    #[tracing::instrument(name = "Sending email with failover", skip_all)]
+    pub async fn deliver(&self, recipient: &Email, message: &EmailMessage) -> Result<&str> {
+        let mut last_error = None;
+        for provider in &self.providers {
+            if !provider.breaker.allow_request() {
+                tracing::debug!(provider = %provider.name, "skipping provider with open circuit");
+                continue;
+            }
+            match provider.client.send_email(recipient, message).await {
+                Ok(()) => {
+                    provider.breaker.record_success();
+                    return Ok(&provider.name);
+                }
+                // Another provider would reject the email just the same
+                Err(e) if EmailDeliveryError::is_permanent(&e) => {
+                    provider.breaker.record_success();
+                    return Err(e);
+                }
+                Err(e) => {
+                    tracing::warn!(provider = %provider.name, error = ?e, "email provider failed");
+                    provider.breaker.record_failure();
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            EmailDeliveryError::Retryable(eyre!("no email provider is available")).into()
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FailoverEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let provider = self.deliver(recipient, message).await?;
+        tracing::info!(provider, "email delivered");
+        Ok(())
+    }
+
+    // Email can still be delivered as long as one provider is reachable
+    async fn check_health(&self) -> Result<()> {
+        let mut failures = Vec::new();
+        for provider in &self.providers {
+            match provider.client.check_health().await {
+                Ok(()) => return Ok(()),
+                Err(e) => failures.push(format!("{}: {e}", provider.name)),
+            }
+        }
+        Err(eyre!(
+            "no email provider is reachable ({})",
+            failures.join(", ")
+        ))
+    }
+}
+
+struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    // Set while the circuit is open
+    opened_at: Option<Instant>,
+    // When the trial request after the cooldown was let through. A trial that never reports
+    // back, e.g. because the send was cancelled, stops blocking others after another cooldown.
+    trial_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(opened_at) = state.opened_at else {
+            return true;
+        };
+        let trial_pending = state
+            .trial_started_at
+            .is_some_and(|started_at| started_at.elapsed() < self.cooldown);
+        if trial_pending || opened_at.elapsed() < self.cooldown {
+            return false;
+        }
+        state.trial_started_at = Some(Instant::now());
+        true
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.trial_started_at.is_some() || state.consecutive_failures >= self.failure_threshold
+        {
+            state.opened_at = Some(Instant::now());
+            state.trial_started_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Copy)]
+    enum Outcome {
+        Deliver,
+        FailRetryable,
+        FailPermanent,
+    }
+
+    struct StubEmailClient {
+        outcome: Outcome,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for StubEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.outcome {
+                Outcome::Deliver => Ok(()),
+                Outcome::FailRetryable => {
+                    Err(EmailDeliveryError::Retryable(eyre!("provider down")).into())
+                }
+                Outcome::FailPermanent => {
+                    Err(EmailDeliveryError::Permanent(eyre!("invalid recipient")).into())
+                }
+            }
+        }
+    }
+
+    fn stub(outcome: Outcome) -> Arc<StubEmailClient> {
+        Arc::new(StubEmailClient {
+            outcome,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    fn failover(
+        primary: &Arc<StubEmailClient>,
+        secondary: &Arc<StubEmailClient>,
+        cooldown: Duration,
+    ) -> FailoverEmailClient {
+        FailoverEmailClient::new(
+            vec![
+                ("primary".to_owned(), primary.clone()),
+                ("secondary".to_owned(), secondary.clone()),
+            ],
+            2,
+            cooldown,
+        )
+    }
+
+    fn recipient() -> Email {
+        Email::parse(SecretString::from("test@example.com")).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "subject".to_owned(),
+            html_body: "<p>body</p>".to_owned(),
+            text_body: "body".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_use_first_provider_that_delivers() {
+        let primary = stub(Outcome::FailRetryable);
+        let secondary = stub(Outcome::Deliver);
+        let client = failover(&primary, &secondary, Duration::from_secs(60));
+
+        let provider = client.deliver(&recipient(), &message()).await.unwrap();
+
+        assert_eq!(provider, "secondary");
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_not_fail_over_on_permanent_error() {
+        let primary = stub(Outcome::FailPermanent);
+        let secondary = stub(Outcome::Deliver);
+        let client = failover(&primary, &secondary, Duration::from_secs(60));
+
+        let error = client.deliver(&recipient(), &message()).await.unwrap_err();
+
+        assert!(EmailDeliveryError::is_permanent(&error));
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn should_skip_provider_with_open_circuit() {
+        let primary = stub(Outcome::FailRetryable);
+        let secondary = stub(Outcome::Deliver);
+        let client = failover(&primary, &secondary, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            client.deliver(&recipient(), &message()).await.unwrap();
+        }
+
+        // The circuit opened after two failures
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_retry_provider_after_cooldown() {
+        let primary = stub(Outcome::FailRetryable);
+        let secondary = stub(Outcome::Deliver);
+        let client = failover(&primary, &secondary, Duration::ZERO);
+
+        for _ in 0..3 {
+            client.deliver(&recipient(), &message()).await.unwrap();
+        }
+
+        // Each attempt after the cooldown is a single trial request
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_fail_if_every_provider_fails() {
+        let primary = stub(Outcome::FailRetryable);
+        let secondary = stub(Outcome::FailRetryable);
+        let client = failover(&primary, &secondary, Duration::from_secs(60));
+
+        let error = client.deliver(&recipient(), &message()).await.unwrap_err();
+
+        assert!(!EmailDeliveryError::is_permanent(&error));
+    }
+
+    #[tokio::test]
+    async fn should_allow_new_trial_if_previous_one_never_finished() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+        breaker.record_failure();
+        assert!(!breaker.allow_request());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // The trial is let through, but never records its outcome, e.g. it was cancelled
+        assert!(breaker.allow_request());
+        assert!(!breaker.allow_request());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(breaker.allow_request());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::domain::{EmailOutbox, EmailOutboxError, OutboxEmail, OutboxEntry, OutboxStatus};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+// In-memory email outbox, used by tests and local development
+#[derive(Default)]
+pub struct HashMapEmailOutbox {
+    state: RwLock<OutboxState>,
+}
+
+#[derive(Default)]
+struct OutboxState {
+    next_id: i64,
+    // Entries keyed by id, each with the earliest time it may be attempted again
+    entries: HashMap<i64, (OutboxEntry, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for HashMapEmailOutbox {
+    async fn enqueue(&self, email: OutboxEmail) -> Result<bool, EmailOutboxError> {
+        let mut state = self.state.write().await;
+        if state
+            .entries
+            .values()
+            .any(|(entry, _)| entry.email.idempotency_key == email.idempotency_key)
+        {
+            return Ok(false);
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        let entry = OutboxEntry {
+            id,
+            email,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+        };
+        state.entries.insert(id, (entry, Utc::now()));
+        Ok(true)
+    }
+
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxError> {
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(lease)
+            .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+        let mut state = self.state.write().await;
+        let mut due: Vec<_> = state
+            .entries
+            .values_mut()
+            .filter(|(entry, next_attempt_at)| {
+                entry.status == OutboxStatus::Pending && *next_attempt_at <= now
+            })
+            .collect();
+        due.sort_by_key(|(entry, _)| entry.id);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|(entry, next_attempt_at)| {
+                entry.attempts += 1;
+                *next_attempt_at = now + lease;
+                entry.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(&self, id: i64) -> Result<(), EmailOutboxError> {
+        let mut state = self.state.write().await;
+        let (entry, _) = state
+            .entries
+            .get_mut(&id)
+            .ok_or(EmailOutboxError::EntryNotFound)?;
+        entry.status = OutboxStatus::Delivered;
+        scrub_body(entry);
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError> {
+        let mut state = self.state.write().await;
+        let (entry, next_attempt_at) = state
+            .entries
+            .get_mut(&id)
+            .ok_or(EmailOutboxError::EntryNotFound)?;
+        entry.last_error = Some(error.to_owned());
+        match retry_at {
+            Some(retry_at) => *next_attempt_at = retry_at,
+            None => {
+                entry.status = OutboxStatus::Dead;
+                scrub_body(entry);
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_entry(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxEntry>, EmailOutboxError> {
+        let state = self.state.read().await;
+        Ok(state
+            .entries
+            .values()
+            .find(|(entry, _)| entry.email.idempotency_key == idempotency_key)
+            .map(|(entry, _)| entry.clone()))
+    }
+}
+
+fn scrub_body(entry: &mut OutboxEntry) {
+    entry.email.message.html_body.clear();
+    entry.email.message.text_body.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, EmailMessage};
+    use secrecy::SecretString;
+
+    fn outbox_email(idempotency_key: &str) -> OutboxEmail {
+        OutboxEmail {
+            idempotency_key: idempotency_key.to_owned(),
+            recipient: Email::parse(SecretString::from("test@example.com")).unwrap(),
+            message: EmailMessage {
+                subject: "subject".to_owned(),
+                html_body: "<p>body</p>".to_owned(),
+                text_body: "body".to_owned(),
+            },
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_is_idempotent() {
+        let outbox = HashMapEmailOutbox::default();
+
+        assert_eq!(outbox.enqueue(outbox_email("key")).await, Ok(true));
+        assert_eq!(outbox.enqueue(outbox_email("key")).await, Ok(false));
+
+        let claimed = outbox.claim_due(10, Duration::from_secs(60)).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_entries_are_leased() {
+        let outbox = HashMapEmailOutbox::default();
+        outbox.enqueue(outbox_email("key")).await.unwrap();
+
+        let claimed = outbox.claim_due(10, Duration::from_secs(60)).await.unwrap();
+        assert_eq!(claimed[0].attempts, 1);
+        let claimed = outbox.claim_due(10, Duration::from_secs(60)).await.unwrap();
+        assert!(claimed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mark_delivered_scrubs_body() {
+        let outbox = HashMapEmailOutbox::default();
+        outbox.enqueue(outbox_email("key")).await.unwrap();
+        let id = outbox.claim_due(10, Duration::from_secs(60)).await.unwrap()[0].id;
+
+        outbox.mark_delivered(id).await.unwrap();
+
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Delivered);
+        assert_eq!(entry.email.message.subject, "subject");
+        assert!(entry.email.message.html_body.is_empty());
+        assert!(entry.email.message.text_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed() {
+        let outbox = HashMapEmailOutbox::default();
+        outbox.enqueue(outbox_email("key")).await.unwrap();
+        let id = outbox.claim_due(10, Duration::from_secs(60)).await.unwrap()[0].id;
+
+        outbox
+            .mark_failed(id, "boom", Some(Utc::now()))
+            .await
+            .unwrap();
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Pending);
+        assert_eq!(entry.last_error.as_deref(), Some("boom"));
+        assert_eq!(entry.email.message.text_body, "body");
+
+        outbox.mark_failed(id, "boom", None).await.unwrap();
+        let entry = outbox.get_entry("key").await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Dead);
+        assert_eq!(entry.email.message.subject, "subject");
+        assert!(entry.email.message.html_body.is_empty());
+        assert!(entry.email.message.text_body.is_empty());
+        assert_eq!(
+            outbox.mark_delivered(42).await,
+            Err(EmailOutboxError::EntryNotFound)
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use crate::domain::{Device, DeviceSighting, Email, KnownDeviceStore, KnownDeviceStoreError};
+use dashmap::DashMap;
+use std::collections::HashSet;
+
+#[derive(Default)]
+pub struct HashMapKnownDeviceStore {
+    devices: DashMap<Email, HashSet<Device>>,
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for HashMapKnownDeviceStore {
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: Device,
+    ) -> Result<DeviceSighting, KnownDeviceStoreError> {
+        let mut devices = self.devices.entry(email.clone()).or_default();
+        let first = devices.is_empty();
+        Ok(match (devices.insert(device), first) {
+            (false, _) => DeviceSighting::Known,
+            (true, true) => DeviceSighting::First,
+            (true, false) => DeviceSighting::New,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn device(ip: &str) -> Device {
+        Device {
+            ip: ip.to_owned(),
+            user_agent: "test-agent".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remember_device() {
+        let store = HashMapKnownDeviceStore::default();
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+
+        let result = store.remember_device(&email, device("10.0.0.1")).await;
+        assert_eq!(result, Ok(DeviceSighting::First));
+
+        let result = store.remember_device(&email, device("10.0.0.1")).await;
+        assert_eq!(result, Ok(DeviceSighting::Known));
+
+        let result = store.remember_device(&email, device("10.0.0.2")).await;
+        assert_eq!(result, Ok(DeviceSighting::New));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::domain::{
+    Email, PhoneVerification, PhoneVerificationStore, PhoneVerificationStoreError,
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::time::Duration;
+
+// In-memory phone verification store, used by tests and local development
+pub struct HashMapPhoneVerificationStore {
+    entries: DashMap<Email, Entry>,
+    code_ttl: chrono::Duration,
+    max_sends: u32,
+    send_window: chrono::Duration,
+}
+
+struct Entry {
+    pending: Option<(PhoneVerification, DateTime<Utc>)>,
+    window_started_at: DateTime<Utc>,
+    sends: u32,
+}
+
+impl HashMapPhoneVerificationStore {
+    pub fn new(code_ttl: Duration, max_sends: u32, send_window: Duration) -> Self {
+        Self {
+            entries: DashMap::new(),
+            code_ttl: chrono::Duration::from_std(code_ttl).unwrap_or(chrono::Duration::MAX),
+            max_sends,
+            send_window: chrono::Duration::from_std(send_window).unwrap_or(chrono::Duration::MAX),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for HashMapPhoneVerificationStore {
+    async fn add_code(
+        &self,
+        email: Email,
+        verification: PhoneVerification,
+    ) -> Result<(), PhoneVerificationStoreError> {
+        let now = Utc::now();
+        let mut entry = self.entries.entry(email).or_insert(Entry {
+            pending: None,
+            window_started_at: now,
+            sends: 0,
+        });
+        if entry.window_started_at + self.send_window <= now {
+            entry.window_started_at = now;
+            entry.sends = 0;
+        }
+        if entry.sends >= self.max_sends {
+            return Err(PhoneVerificationStoreError::TooManyCodesSent);
+        }
+        entry.sends += 1;
+        entry.pending = Some((verification, now + self.code_ttl));
+        Ok(())
+    }
+
+    // The send count is kept, so verifying doesn't reset the limit
+    async fn remove_code(&self, email: &Email) -> Result<(), PhoneVerificationStoreError> {
+        let mut entry = self
+            .entries
+            .get_mut(email)
+            .ok_or(PhoneVerificationStoreError::VerificationNotFound)?;
+        match entry.pending.take() {
+            Some((_, expires_at)) if expires_at > Utc::now() => Ok(()),
+            _ => Err(PhoneVerificationStoreError::VerificationNotFound),
+        }
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<PhoneVerification, PhoneVerificationStoreError> {
+        self.entries
+            .get(email)
+            .and_then(|entry| match &entry.pending {
+                Some((verification, expires_at)) if *expires_at > Utc::now() => {
+                    Some(verification.clone())
+                }
+                _ => None,
+            })
+            .ok_or(PhoneVerificationStoreError::VerificationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LoginAttemptId, PhoneNumber, TwoFACode};
+    use secrecy::SecretString;
+
+    fn verification() -> PhoneVerification {
+        PhoneVerification {
+            phone_number: PhoneNumber::parse(SecretString::from("+14155552671")).unwrap(),
+            login_attempt_id: LoginAttemptId::default(),
+            code: TwoFACode::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_consume_code_once() {
+        let store =
+            HashMapPhoneVerificationStore::new(Duration::from_secs(60), 5, Duration::from_secs(60));
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let verification = verification();
+
+        store
+            .add_code(email.clone(), verification.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get_code(&email).await, Ok(verification));
+        assert_eq!(store.remove_code(&email).await, Ok(()));
+        assert_eq!(
+            store.remove_code(&email).await,
+            Err(PhoneVerificationStoreError::VerificationNotFound)
+        );
+        assert_eq!(
+            store.get_code(&email).await,
+            Err(PhoneVerificationStoreError::VerificationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_limit_codes_sent_per_window() {
+        let store =
+            HashMapPhoneVerificationStore::new(Duration::from_secs(60), 2, Duration::from_secs(60));
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+
+        for _ in 0..2 {
+            store.add_code(email.clone(), verification()).await.unwrap();
+            // Using a code doesn't give the user more sends
+            store.remove_code(&email).await.unwrap();
+        }
+        assert_eq!(
+            store.add_code(email.clone(), verification()).await,
+            Err(PhoneVerificationStoreError::TooManyCodesSent)
+        );
+
+        let store = HashMapPhoneVerificationStore::new(Duration::from_secs(60), 1, Duration::ZERO);
+        store.add_code(email.clone(), verification()).await.unwrap();
+        assert_eq!(store.add_code(email.clone(), verification()).await, Ok(()));
+    }
+}
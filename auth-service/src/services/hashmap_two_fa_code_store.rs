@@ -1,18 +1,17 @@
 use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
 use color_eyre::Result;
-use color_eyre::eyre::eyre;
-use std::collections::HashMap;
+use dashmap::DashMap;
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: DashMap<Email, (LoginAttemptId, TwoFACode)>,
 }
 
 // implement TwoFACodeStore for HashmapTwoFACodeStore
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
@@ -21,13 +20,11 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        if self.codes.remove(email).is_none() {
-            Err(TwoFACodeStoreError::UnexpectedError(eyre!(
-                "Email not found in HashmapTwoFACodeStore"
-            )))?
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
-        Ok(())
     }
 
     async fn get_code(
@@ -35,10 +32,8 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
         match self.codes.get(email) {
-            Some(v) => Ok(v.clone()),
-            None => Err(TwoFACodeStoreError::UnexpectedError(eyre!(
-                "Email not found in HashmapTwoFACodeStore"
-            ))),
+            Some(v) => Ok(v.value().clone()),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 }
@@ -49,7 +44,7 @@ mod tests {
 
     #[tokio::test]
     async fn should_add_code_successfully() {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(SecretString::from("test@example.com")).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
@@ -62,7 +57,7 @@ mod tests {
 
     #[tokio::test]
     async fn should_remove_code_successfully() {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(SecretString::from("test@example.com")).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
@@ -75,9 +70,18 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn should_return_not_found_when_removing_non_existent_code() {
+        let store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+
+        let result = store.remove_code(&email).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
     #[tokio::test]
     async fn should_get_code_successfully() {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(SecretString::from("test@example.com")).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
@@ -97,10 +101,6 @@ mod tests {
         let email = Email::parse(SecretString::from("test@example.com")).unwrap();
 
         let result = store.get_code(&email).await;
-        assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err(),
-            TwoFACodeStoreError::UnexpectedError(eyre!("Email not found in HashmapTwoFACodeStore"))
-        );
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 }
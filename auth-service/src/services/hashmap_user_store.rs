@@ -1,22 +1,28 @@
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
-use std::collections::HashMap;
+use crate::domain::{
+    AccountStatus, Email, Password, PhoneNumber, TwoFAChannel, User, UserPage, UserQuery,
+    UserStore, UserStoreError,
+};
+use chrono::Utc;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use secrecy::ExposeSecret;
 
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: DashMap<Email, User>,
 }
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
         // Return `UserStoreError::UserAlreadyExists` if the user already exists,
         // otherwise insert the user into the hashmap and return `Ok(())`.
-        if self.users.contains_key(&user.email) {
-            return Err(UserStoreError::UserAlreadyExists);
-        };
-
-        self.users.insert(user.email.clone(), user);
-
-        Ok(())
+        match self.users.entry(user.email.clone()) {
+            Entry::Occupied(_) => Err(UserStoreError::UserAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(user);
+                Ok(())
+            }
+        }
     }
 
     // Implement a public method called `get_user`, which takes an
@@ -26,7 +32,7 @@ impl UserStore for HashmapUserStore {
     // Return `UserStoreError::UserNotFound` if the user can not be found.
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         match self.users.get(email) {
-            Some(user) => Ok(user.clone()),
+            Some(user) => Ok(user.value().clone()),
             None => Err(UserStoreError::UserNotFound),
         }
     }
@@ -48,18 +54,135 @@ impl UserStore for HashmapUserStore {
         }
         Ok(())
     }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let search = query.search.as_ref().map(|search| search.to_lowercase());
+        let mut users: Vec<User> = self
+            .users
+            .iter()
+            .map(|entry| entry.value().clone())
+            .filter(|user| match &search {
+                Some(search) => user
+                    .email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(search),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        Ok(UserPage {
+            total: users.len() as u64,
+            users: users
+                .into_iter()
+                .skip(query.offset as usize)
+                .take(query.limit as usize)
+                .collect(),
+        })
+    }
+
+    async fn set_status(&self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.status = status;
+        Ok(())
+    }
+
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn revoke_tokens(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.tokens_revoked_at = Some(Utc::now());
+        user.token_version += 1;
+        Ok(())
+    }
+
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.phone_number = Some(phone_number);
+        user.phone_verified = false;
+        user.two_fa_channel = TwoFAChannel::Email;
+        Ok(())
+    }
+
+    async fn mark_phone_verified(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.phone_verified = user.phone_number.is_some();
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_channel = channel;
+        Ok(())
+    }
 }
 
 // Add unit tests for your `HashmapUserStore` implementation
 #[cfg(test)]
 mod tests {
-    use crate::domain::{Email, Password, User, UserStore};
+    use crate::domain::{
+        AccountStatus, Email, Password, PhoneNumber, TwoFAChannel, User, UserQuery, UserStore,
+    };
     use crate::services::hashmap_user_store::{HashmapUserStore, UserStoreError};
     use secrecy::SecretString;
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse(SecretString::from("test@test.com")).unwrap();
         let password = Password::parse(SecretString::from("password")).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
@@ -69,9 +192,32 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_add_user_concurrently() {
+        let user_store = std::sync::Arc::new(HashmapUserStore::default());
+        let email = Email::parse(SecretString::from("test@test.com")).unwrap();
+        let password = Password::parse(SecretString::from("password")).unwrap();
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..10 {
+            let user_store = user_store.clone();
+            let user = User::new(email.clone(), password.clone(), false);
+            tasks.spawn(async move { user_store.add_user(user).await });
+        }
+        let results = tasks.join_all().await;
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(
+            results
+                .iter()
+                .filter(|result| result.is_err())
+                .all(|result| *result == Err(UserStoreError::UserAlreadyExists))
+        );
+    }
+
     #[tokio::test]
     async fn test_get_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse(SecretString::from("test@test.com")).unwrap();
         let password = Password::parse(SecretString::from("password")).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
@@ -86,7 +232,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse(SecretString::from("test@test.com")).unwrap();
         let password = Password::parse(SecretString::from("password")).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
@@ -109,4 +255,126 @@ mod tests {
             .await;
         assert_eq!(result, Err(UserStoreError::InvalidCredentials));
     }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let user_store = HashmapUserStore::default();
+        for address in ["carol@test.com", "alice@test.com", "bob@example.com"] {
+            let email = Email::parse(SecretString::from(address)).unwrap();
+            let password = Password::parse(SecretString::from("password")).unwrap();
+            let result = user_store.add_user(User::new(email, password, false)).await;
+            assert!(result.is_ok());
+        }
+
+        let query = UserQuery {
+            search: Some("TEST.COM".to_owned()),
+            offset: 0,
+            limit: 1,
+        };
+        let page = user_store.list_users(&query).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.users.len(), 1);
+        assert_eq!(
+            page.users[0].email,
+            Email::parse(SecretString::from("alice@test.com")).unwrap()
+        );
+
+        let query = UserQuery {
+            search: None,
+            offset: 2,
+            limit: 10,
+        };
+        let page = user_store.list_users(&query).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(
+            page.users[0].email,
+            Email::parse(SecretString::from("carol@test.com")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phone_number_verification() {
+        let user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::from("test@test.com")).unwrap();
+        let password = Password::parse(SecretString::from("password")).unwrap();
+        assert!(
+            user_store
+                .add_user(User::new(email.clone(), password, true))
+                .await
+                .is_ok()
+        );
+        let phone_number = PhoneNumber::parse(SecretString::from("+14155552671")).unwrap();
+
+        assert!(
+            user_store
+                .set_phone_number(&email, phone_number.clone())
+                .await
+                .is_ok()
+        );
+        assert!(user_store.mark_phone_verified(&email).await.is_ok());
+        assert!(
+            user_store
+                .set_two_fa_channel(&email, TwoFAChannel::Sms)
+                .await
+                .is_ok()
+        );
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.sms_phone_number(), Some(&phone_number));
+
+        // A new number has to be verified again before codes are texted to it
+        let new_number = PhoneNumber::parse(SecretString::from("+442071838750")).unwrap();
+        assert!(
+            user_store
+                .set_phone_number(&email, new_number)
+                .await
+                .is_ok()
+        );
+        let user = user_store.get_user(&email).await.unwrap();
+        assert!(!user.phone_verified);
+        assert_eq!(user.two_fa_channel, TwoFAChannel::Email);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_flags() {
+        let user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::from("test@test.com")).unwrap();
+        let password = Password::parse(SecretString::from("password")).unwrap();
+        let user = User::new(email.clone(), password, false);
+        assert!(user_store.add_user(user).await.is_ok());
+
+        assert!(
+            user_store
+                .set_status(&email, AccountStatus::Disabled)
+                .await
+                .is_ok()
+        );
+        assert!(user_store.set_requires_2fa(&email, true).await.is_ok());
+        assert!(user_store.revoke_tokens(&email).await.is_ok());
+        let new_password = Password::parse(SecretString::from("new_password")).unwrap();
+        assert!(
+            user_store
+                .update_password(&email, new_password.clone())
+                .await
+                .is_ok()
+        );
+
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.status, AccountStatus::Disabled);
+        assert!(user.requires_2fa);
+        assert!(user.tokens_revoked_at.is_some());
+        assert!(
+            user_store
+                .validate_user(&email, &new_password)
+                .await
+                .is_ok()
+        );
+
+        let unknown = Email::parse(SecretString::from("notExist@notExist.com")).unwrap();
+        assert_eq!(
+            user_store
+                .set_status(&unknown, AccountStatus::Disabled)
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
 }
@@ -1,14 +1,14 @@
 use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use dashmap::DashSet;
 use secrecy::{ExposeSecret, SecretString};
-use std::collections::HashSet;
 
 #[derive(Default)]
 pub struct HashSetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: DashSet<String>,
 }
 #[async_trait::async_trait]
 impl BannedTokenStore for HashSetBannedTokenStore {
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
+    async fn add_token(&self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
         self.tokens.insert(token.expose_secret().to_owned());
         Ok(())
     }
@@ -25,7 +25,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_is_banned() {
-        let mut token_store = HashSetBannedTokenStore::default();
+        let token_store = HashSetBannedTokenStore::default();
         let token = SecretString::from("token");
         let result = token_store.add_token(&token).await;
         assert!(result.is_ok());
@@ -35,7 +35,7 @@ mod tests {
     }
     #[tokio::test]
     async fn test_add_token() {
-        let mut token_store = HashSetBannedTokenStore::default();
+        let token_store = HashSetBannedTokenStore::default();
         let token = SecretString::from("token");
 
         let result = token_store.add_token(&token).await;
@@ -43,7 +43,7 @@ mod tests {
     }
     #[tokio::test]
     async fn test_add_token_already_exists() {
-        let mut token_store = HashSetBannedTokenStore::default();
+        let token_store = HashSetBannedTokenStore::default();
         let token = SecretString::from("token");
 
         let _result = token_store.add_token(&token).await;
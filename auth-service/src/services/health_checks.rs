@@ -0,0 +1,66 @@
+use crate::domain::HealthCheck;
+use crate::{EmailClientType, RedisConnection};
+use color_eyre::eyre::{Context, Result};
+use sqlx::{Connection as _, PgPool};
+
+// Pings Postgres through the pool, so a pool exhausted by slow queries also shows up as down
+pub struct PostgresHealthCheck {
+    pool: PgPool,
+}
+
+impl PostgresHealthCheck {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for PostgresHealthCheck {
+    async fn check(&self) -> Result<()> {
+        let mut connection = self
+            .pool
+            .acquire()
+            .await
+            .wrap_err("failed to acquire a Postgres connection")?;
+        connection.ping().await.wrap_err("failed to ping Postgres")
+    }
+}
+
+// Pings Redis over the connection shared by the token and 2FA code stores
+pub struct RedisHealthCheck {
+    conn: RedisConnection,
+}
+
+impl RedisHealthCheck {
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for RedisHealthCheck {
+    async fn check(&self) -> Result<()> {
+        redis::cmd("PING")
+            .query_async::<String>(&mut self.conn.clone())
+            .await
+            .wrap_err("failed to ping Redis")?;
+        Ok(())
+    }
+}
+
+pub struct EmailProviderHealthCheck {
+    email_client: EmailClientType,
+}
+
+impl EmailProviderHealthCheck {
+    pub fn new(email_client: EmailClientType) -> Self {
+        Self { email_client }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for EmailProviderHealthCheck {
+    async fn check(&self) -> Result<()> {
+        self.email_client.check_health().await
+    }
+}
@@ -0,0 +1,21 @@
+use crate::domain::{PhoneNumber, SmsClient};
+use color_eyre::Result;
+use secrecy::ExposeSecret;
+
+// SMS client used when no SMS provider is configured, e.g. in local development
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        // Nothing is sent, the message is only logged so the code can be read from the output
+        tracing::info!(
+            "Sending SMS to {} with content: {}",
+            recipient.as_ref().expose_secret(),
+            body
+        );
+
+        Ok(())
+    }
+}
@@ -1,13 +1,39 @@
 mod data_stores;
+mod dev_mailbox_email_client;
+mod email_outbox_worker;
+mod failover_email_client;
+mod hashmap_email_outbox;
+mod hashmap_known_device_store;
+mod hashmap_phone_verification_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
 mod hashset_banned_token_store;
+mod health_checks;
 mod mock_email_client;
+mod mock_sms_client;
 mod postmark_email_client;
+mod smtp_email_client;
+mod token_store_purger;
+mod twilio_sms_client;
+mod vec_audit_sink;
+mod vec_email_delivery_store;
 
 pub use data_stores::*;
+pub use dev_mailbox_email_client::*;
+pub use email_outbox_worker::*;
+pub use failover_email_client::*;
+pub use hashmap_email_outbox::*;
+pub use hashmap_known_device_store::*;
+pub use hashmap_phone_verification_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+pub use health_checks::*;
 pub use mock_email_client::*;
+pub use mock_sms_client::*;
 pub use postmark_email_client::*;
+pub use smtp_email_client::*;
+pub use token_store_purger::*;
+pub use twilio_sms_client::*;
+pub use vec_audit_sink::*;
+pub use vec_email_delivery_store::*;
@@ -1,11 +1,13 @@
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, eyre};
 // For improved error handling and reporting
-use reqwest::{Client, Url};
+use reqwest::header::ACCEPT;
+use reqwest::{Client, StatusCode, Url};
 // For making HTTP requests
 use secrecy::{ExposeSecret, SecretString};
 // For securely handling sensitive data
 
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, EmailDeliveryError, EmailMessage};
+use crate::utils::trace_context_headers;
 // Import domain-specific modules
 
 // Define the PostmarkEmailClient struct
@@ -36,7 +38,7 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
@@ -45,9 +47,9 @@ impl EmailClient for PostmarkEmailClient {
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
@@ -55,22 +57,75 @@ impl EmailClient for PostmarkEmailClient {
         let request = self
             .http_client
             .post(url)
+            .headers(trace_context_headers())
             .header(
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(), // Securely expose the authorization token
             )
             .json(&request_body);
 
-        // Send the request and handle the response
-        request.send().await?.error_for_status()?;
+        // Send the request and classify failures. Postmark answers 422 for many problems, but
+        // only some of them are about the email itself. The rest, like an unconfirmed sender
+        // signature, are specific to this account, so another attempt or provider may succeed.
+        let response = request
+            .send()
+            .await
+            .map_err(|e| EmailDeliveryError::Retryable(e.into()))?;
+        if response.status() == StatusCode::UNPROCESSABLE_ENTITY {
+            let error = response
+                .json::<PostmarkErrorResponse>()
+                .await
+                .map_err(|e| EmailDeliveryError::Retryable(e.into()))?;
+            let report = eyre!(
+                "Postmark rejected the email with error code {}: {}",
+                error.error_code,
+                error.message
+            );
+            return Err(match PERMANENT_ERROR_CODES.contains(&error.error_code) {
+                true => EmailDeliveryError::Permanent(report),
+                false => EmailDeliveryError::Retryable(report),
+            }
+            .into());
+        }
+        response
+            .error_for_status()
+            .map_err(|e| EmailDeliveryError::Retryable(e.into()))?;
 
         Ok(())
     }
+
+    // Fetches the server settings, which needs a valid token but sends nothing
+    async fn check_health(&self) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/server")?;
+        self.http_client
+            .get(url)
+            .headers(trace_context_headers())
+            .header(
+                POSTMARK_AUTH_HEADER,
+                self.authorization_token.expose_secret(),
+            )
+            .header(ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
 }
 
 // Constants for message stream and authorization header
 const MESSAGE_STREAM: &str = "outbound";
 const POSTMARK_AUTH_HEADER: &str = "X-Postmark-Server-Token";
+// Error codes that mean the email can never be delivered: an invalid email request
+// and an inactive recipient
+const PERMANENT_ERROR_CODES: [i64; 2] = [300, 406];
+
+// Body of a Postmark error response, see https://postmarkapp.com/developer/api/overview#error-codes
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkErrorResponse {
+    error_code: i64,
+    message: String,
+}
 
 // Define the structure of the email request body
 // For more information about the request structure, see the API docs: https://postmarkapp.com/developer/user-guide/send-email-with-api
@@ -96,14 +151,14 @@ mod tests {
     use wiremock::matchers::{any, header, header_exists, method, path};
     use wiremock::{Mock, MockServer, Request, ResponseTemplate};
 
-    // Helper function to generate a test subject
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
-
-    // Helper function to generate test content
-    fn content() -> String {
-        Paragraph(1..10).fake()
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        let content: String = Paragraph(1..10).fake();
+        EmailMessage {
+            subject: Sentence(1..2).fake(),
+            html_body: format!("<p>{content}</p>"),
+            text_body: content,
+        }
     }
 
     // Helper function to generate a test email
@@ -162,8 +217,36 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_propagates_the_trace_context() {
+        use opentelemetry::trace::TracerProvider;
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+        use tracing::Instrument;
+        use tracing::instrument::WithSubscriber;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(header_exists("traceparent"))
+            .and(path("/email"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Spans only carry a trace context when they are exported
+        let tracer_provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")));
         let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+            .send_email(&email(), &message())
+            .instrument(tracing::info_span!("request"))
+            .with_subscriber(subscriber)
             .await;
 
         assert!(outcome.is_ok());
@@ -183,11 +266,57 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        let error = outcome.unwrap_err();
+        assert!(!EmailDeliveryError::is_permanent(&error));
+    }
+
+    // Test to classify rejected emails as permanent failures
+    #[tokio::test]
+    async fn send_email_fails_permanently_if_the_server_returns_422() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // Set up the mock server to reject the recipient
+        let body = serde_json::json!({ "ErrorCode": 300, "Message": "Invalid 'To' address" });
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422).set_body_json(body))
+            .expect(1)
+            .mount(&mock_server)
             .await;
 
-        assert!(outcome.is_err());
+        // Execute the send_email function and check the outcome
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        let error = outcome.unwrap_err();
+        assert!(EmailDeliveryError::is_permanent(&error));
+        assert!(format!("{error:#}").contains("Invalid 'To' address"));
+    }
+
+    // Test to keep account problems retryable, since another provider can still deliver
+    #[tokio::test]
+    async fn send_email_fails_retryably_if_the_422_is_about_the_account() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // Set up the mock server to reject the sender signature
+        let body = serde_json::json!({
+            "ErrorCode": 400,
+            "Message": "The 'From' address you supplied is not a Sender Signature on your account."
+        });
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422).set_body_json(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Execute the send_email function and check the outcome
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        let error = outcome.unwrap_err();
+        assert!(!EmailDeliveryError::is_permanent(&error));
+        assert!(format!("{error:#}").contains("error code 400"));
     }
 
     // Test to handle request timeouts
@@ -205,10 +334,9 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
-        assert!(outcome.is_err());
+        let error = outcome.unwrap_err();
+        assert!(!EmailDeliveryError::is_permanent(&error));
     }
 }
@@ -0,0 +1,336 @@
+use crate::domain::{Email, EmailClient, EmailDeliveryError, EmailMessage};
+use color_eyre::eyre::{Result, eyre};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::PoolConfig;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+
+// How the connection to the SMTP server is secured
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SmtpTls {
+    // Plain text, only suitable for local relays and tests
+    None,
+    // Connect in plain text and upgrade with STARTTLS, usually on port 587
+    StartTls,
+    // TLS from the first byte, usually on port 465
+    Implicit,
+}
+
+impl SmtpTls {
+    pub fn parse(tls: &str) -> Result<Self> {
+        match tls {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Implicit),
+            _ => Err(eyre!("{} is not a valid SMTP TLS mode", tls)),
+        }
+    }
+}
+
+pub struct SmtpCredentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub credentials: Option<SmtpCredentials>,
+    pub timeout: Duration,
+    pub pool_size: u32,
+}
+
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(settings: SmtpSettings, sender: Email) -> Result<Self> {
+        let builder = match settings.tls {
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+            }
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+        };
+        let mut builder = builder
+            .port(settings.port)
+            .timeout(Some(settings.timeout))
+            .pool_config(PoolConfig::new().max_size(settings.pool_size));
+        if let Some(credentials) = settings.credentials {
+            builder = builder.credentials(Credentials::new(
+                credentials.username,
+                credentials.password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender: sender.as_ref().expose_secret().parse()?,
+            timeout: settings.timeout,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = Message::builder()
+            .from(self.sender.clone())
+            .to(recipient.as_ref().expose_secret().parse()?)
+            .subject(&message.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.clone(),
+                message.html_body.clone(),
+            ))?;
+
+        // lettre only bounds individual socket operations, so also cap the whole exchange
+        tokio::time::timeout(self.timeout, self.transport.send(email))
+            .await
+            .map_err(|_| {
+                EmailDeliveryError::Retryable(eyre!(
+                    "SMTP server did not respond within {:?}",
+                    self.timeout
+                ))
+            })?
+            // 5xx replies mean the server will never accept this email
+            .map_err(|e| match e.is_permanent() {
+                true => EmailDeliveryError::Permanent(e.into()),
+                false => EmailDeliveryError::Retryable(e.into()),
+            })?;
+        Ok(())
+    }
+
+    // Connects, greets and authenticates without sending a message
+    async fn check_health(&self) -> Result<()> {
+        let connected = tokio::time::timeout(self.timeout, self.transport.test_connection())
+            .await
+            .map_err(|_| eyre!("SMTP server did not respond within {:?}", self.timeout))??;
+        if !connected {
+            return Err(eyre!("SMTP server refused the connection"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const AUTH_PLAIN: &str = "AHNtdHAtdXNlcgBzbXRwLXBhc3N3b3Jk";
+
+    // What the SMTP stand-in saw during one mail transaction
+    #[derive(Clone, Default, Debug)]
+    struct ReceivedMail {
+        auth: Option<String>,
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Behaviour {
+        Accept,
+        RejectSender,
+        Silent,
+    }
+
+    // Minimal in-process SMTP server speaking just enough of RFC 5321 for lettre
+    struct SmtpStandIn {
+        address: SocketAddr,
+        received: Arc<Mutex<Vec<ReceivedMail>>>,
+        connections: Arc<AtomicUsize>,
+    }
+
+    impl SmtpStandIn {
+        async fn start(behaviour: Behaviour) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let connections = Arc::new(AtomicUsize::new(0));
+            let sink = received.clone();
+            let counter = connections.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(handle_connection(stream, behaviour, sink.clone()));
+                }
+            });
+            Self {
+                address,
+                received,
+                connections,
+            }
+        }
+
+        fn received(&self) -> Vec<ReceivedMail> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    async fn handle_connection(
+        stream: TcpStream,
+        behaviour: Behaviour,
+        sink: Arc<Mutex<Vec<ReceivedMail>>>,
+    ) {
+        if behaviour == Behaviour::Silent {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            return;
+        }
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut mail = ReceivedMail::default();
+        writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let command = line.to_ascii_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250-localhost\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n"
+            } else if let Some(auth) = line.strip_prefix("AUTH PLAIN ") {
+                mail.auth = Some(auth.to_owned());
+                b"235 2.7.0 Authentication successful\r\n"
+            } else if command.starts_with("MAIL FROM") {
+                if behaviour == Behaviour::RejectSender {
+                    b"554 5.7.1 Sender rejected\r\n"
+                } else {
+                    mail.from = line[10..].to_owned();
+                    b"250 2.1.0 OK\r\n"
+                }
+            } else if command.starts_with("RCPT TO") {
+                mail.to.push(line[8..].to_owned());
+                b"250 2.1.5 OK\r\n"
+            } else if command == "DATA" {
+                writer
+                    .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                    .await
+                    .unwrap();
+                while let Ok(Some(data)) = lines.next_line().await {
+                    if data == "." {
+                        break;
+                    }
+                    mail.data.push_str(&data);
+                    mail.data.push('\n');
+                }
+                sink.lock().unwrap().push(std::mem::take(&mut mail));
+                b"250 2.0.0 Queued\r\n"
+            } else if command == "QUIT" {
+                writer.write_all(b"221 2.0.0 Bye\r\n").await.unwrap();
+                return;
+            } else {
+                b"250 2.0.0 OK\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(SecretString::from(address)).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your verification code".to_owned(),
+            html_body: "<p>123456</p>".to_owned(),
+            text_body: "123456".to_owned(),
+        }
+    }
+
+    fn email_client(server: &SmtpStandIn) -> SmtpEmailClient {
+        let settings = SmtpSettings {
+            host: server.address.ip().to_string(),
+            port: server.address.port(),
+            tls: SmtpTls::None,
+            credentials: Some(SmtpCredentials {
+                username: "smtp-user".to_owned(),
+                password: SecretString::from("smtp-password"),
+            }),
+            timeout: Duration::from_millis(200),
+            pool_size: 2,
+        };
+        SmtpEmailClient::new(settings, email("sender@example.com")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_message_with_html_and_text_parts() {
+        let server = SmtpStandIn::start(Behaviour::Accept).await;
+        let email_client = email_client(&server);
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+
+        assert!(outcome.is_ok());
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].auth.as_deref(), Some(AUTH_PLAIN));
+        assert_eq!(received[0].from, "<sender@example.com>");
+        assert_eq!(received[0].to, vec!["<recipient@example.com>"]);
+        assert!(received[0].data.contains("Subject: Your verification code"));
+        assert!(received[0].data.contains("multipart/alternative"));
+        assert!(received[0].data.contains("text/plain"));
+        assert!(received[0].data.contains("<p>123456</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_reuses_pooled_connection() {
+        let server = SmtpStandIn::start(Behaviour::Accept).await;
+        let email_client = email_client(&server);
+
+        for _ in 0..2 {
+            let outcome = email_client
+                .send_email(&email("recipient@example.com"), &message())
+                .await;
+            assert!(outcome.is_ok());
+            // Connections are handed back to the pool in the background
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(server.received().len(), 2);
+        assert_eq!(server.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_rejects_the_sender() {
+        let server = SmtpStandIn::start(Behaviour::RejectSender).await;
+        let email_client = email_client(&server);
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+
+        assert!(EmailDeliveryError::is_permanent(&outcome.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_never_answers() {
+        let server = SmtpStandIn::start(Behaviour::Silent).await;
+        let email_client = email_client(&server);
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn should_parse_tls_modes() {
+        assert_eq!(SmtpTls::parse("none").unwrap(), SmtpTls::None);
+        assert_eq!(SmtpTls::parse("starttls").unwrap(), SmtpTls::StartTls);
+        assert_eq!(SmtpTls::parse("tls").unwrap(), SmtpTls::Implicit);
+        assert!(SmtpTls::parse("ssl").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{PostgresBannedTokenStore, PostgresTwoFACodeStore};
+use color_eyre::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Deletes expired banned tokens and 2FA codes from Postgres in the background.
+// Redis expires its keys itself, so this only runs with the Postgres backend.
+pub struct TokenStorePurger {
+    banned_token_store: Arc<PostgresBannedTokenStore>,
+    two_fa_code_store: Arc<PostgresTwoFACodeStore>,
+    interval: Duration,
+}
+
+impl TokenStorePurger {
+    pub fn new(
+        banned_token_store: Arc<PostgresBannedTokenStore>,
+        two_fa_code_store: Arc<PostgresTwoFACodeStore>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            banned_token_store,
+            two_fa_code_store,
+            interval,
+        }
+    }
+
+    // Purges until shutdown, always finishing the purge in progress first
+    pub async fn run(self, shutdown: CancellationToken) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                tracing::error!(error = ?e, "failed to purge expired tokens");
+            }
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.interval) => {}
+            }
+        }
+        tracing::info!("token store purger stopped");
+    }
+
+    // Returns how many expired rows were deleted
+    #[tracing::instrument(name = "Purging expired tokens", skip_all)]
+    pub async fn run_once(&self) -> Result<u64> {
+        let tokens = self.banned_token_store.purge_expired().await?;
+        let codes = self.two_fa_code_store.purge_expired().await?;
+        if tokens + codes > 0 {
+            tracing::debug!(tokens, codes, "purged expired tokens");
+        }
+        Ok(tokens + codes)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::domain::{PhoneNumber, SmsClient};
+use crate::utils::trace_context_headers;
+use color_eyre::eyre::{Result, eyre};
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, SecretString};
+
+// Sends text messages through Twilio's Messages API, or any provider exposing the same API.
+// See https://www.twilio.com/docs/messaging/api/message-resource#create-a-message-resource
+pub struct TwilioSmsClient {
+    http_client: Client,
+    base_url: String,
+    account_sid: String,
+    auth_token: SecretString,
+    sender: PhoneNumber,
+}
+
+impl TwilioSmsClient {
+    pub fn new(
+        base_url: String,
+        account_sid: String,
+        auth_token: SecretString,
+        sender: PhoneNumber,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            account_sid,
+            auth_token,
+            sender,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for TwilioSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        let base = Url::parse(&self.base_url)?;
+        let url = base.join(&format!(
+            "/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        ))?;
+
+        let response = self
+            .http_client
+            .post(url)
+            .headers(trace_context_headers())
+            .basic_auth(&self.account_sid, Some(self.auth_token.expose_secret()))
+            .form(&[
+                ("To", recipient.as_ref().expose_secret()),
+                ("From", self.sender.as_ref().expose_secret()),
+                ("Body", body),
+            ])
+            .send()
+            .await?;
+
+        // Twilio describes rejected messages, e.g. an unreachable number, in the error body
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response
+                .json::<TwilioErrorResponse>()
+                .await
+                .map(|error| format!("error code {}: {}", error.code, error.message))
+                .unwrap_or_else(|_| "no error details".to_owned());
+            return Err(eyre!(
+                "Twilio rejected the SMS with status {status}, {error}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Body of a Twilio error response, see https://www.twilio.com/docs/usage/twilios-response#response-formats-exceptions
+#[derive(serde::Deserialize, Debug)]
+struct TwilioErrorResponse {
+    code: i64,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+    use wiremock::matchers::{any, body_string_contains, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
+
+    fn phone_number(phone_number: &str) -> PhoneNumber {
+        PhoneNumber::parse(SecretString::from(phone_number)).unwrap()
+    }
+
+    fn sms_client(base_url: String) -> TwilioSmsClient {
+        let http_client = Client::builder()
+            .timeout(test::sms_client::TIMEOUT)
+            .build()
+            .unwrap();
+        TwilioSmsClient::new(
+            base_url,
+            ACCOUNT_SID.to_owned(),
+            SecretString::from("auth-token"),
+            phone_number(test::sms_client::SENDER),
+            http_client,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_sms_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(header_exists("Authorization"))
+            .and(path(format!(
+                "/2010-04-01/Accounts/{ACCOUNT_SID}/Messages.json"
+            )))
+            .and(method("POST"))
+            .and(body_string_contains("To=%2B14155552671"))
+            .and(body_string_contains("Body=Your+code+is+123456"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client
+            .send_sms(&phone_number("+14155552671"), "Your code is 123456")
+            .await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_number_is_rejected() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        let body = serde_json::json!({
+            "code": 21211,
+            "message": "The 'To' number is not a valid phone number.",
+            "status": 400
+        });
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400).set_body_json(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client
+            .send_sms(&phone_number("+14155552671"), "Your code is 123456")
+            .await;
+
+        let error = outcome.unwrap_err();
+        assert!(format!("{error:#}").contains("21211"));
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(201).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client
+            .send_sms(&phone_number("+14155552671"), "Your code is 123456")
+            .await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::domain::{AuditEvent, AuditPage, AuditQuery, AuditSink, AuditSinkError};
+use tokio::sync::RwLock;
+
+// In-memory audit sink, used by tests and local development
+#[derive(Default)]
+pub struct VecAuditSink {
+    events: RwLock<Vec<AuditEvent>>,
+}
+
+#[async_trait::async_trait]
+impl AuditSink for VecAuditSink {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditSinkError> {
+        self.events.write().await.push(event);
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<AuditPage, AuditSinkError> {
+        let events = self.events.read().await;
+        let matching: Vec<&AuditEvent> = events
+            .iter()
+            .rev()
+            .filter(|event| query.matches(event))
+            .collect();
+
+        Ok(AuditPage {
+            total: matching.len() as u64,
+            events: matching
+                .into_iter()
+                .skip(query.offset as usize)
+                .take(query.limit as usize)
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditEventKind, Email, RequestMetadata};
+    use secrecy::SecretString;
+
+    #[tokio::test]
+    async fn should_record_and_query_events_newest_first() {
+        let sink = VecAuditSink::default();
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let metadata = RequestMetadata {
+            ip: Some("127.0.0.1".to_owned()),
+            user_agent: Some("test-agent".to_owned()),
+            request_id: Some("request-id".to_owned()),
+        };
+        for kind in [AuditEventKind::Signup, AuditEventKind::LoginSucceeded] {
+            let result = sink
+                .record(AuditEvent::new(kind, Some(&email), &metadata))
+                .await;
+            assert!(result.is_ok());
+        }
+        let other = Email::parse(SecretString::from("other@example.com")).unwrap();
+        let result = sink
+            .record(AuditEvent::new(
+                AuditEventKind::LoginFailed,
+                Some(&other),
+                &metadata,
+            ))
+            .await;
+        assert!(result.is_ok());
+
+        let query = AuditQuery {
+            email: Some("test@example.com".to_owned()),
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        let page = sink.query(&query).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.events[0].kind, AuditEventKind::LoginSucceeded);
+        assert_eq!(page.events[1].ip.as_deref(), Some("127.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn should_filter_events_by_kind() {
+        let sink = VecAuditSink::default();
+        let metadata = RequestMetadata::default();
+        for kind in [AuditEventKind::LoginFailed, AuditEventKind::Logout] {
+            let result = sink.record(AuditEvent::new(kind, None, &metadata)).await;
+            assert!(result.is_ok());
+        }
+
+        let query = AuditQuery {
+            kind: Some(AuditEventKind::Logout),
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        let page = sink.query(&query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].kind, AuditEventKind::Logout);
+    }
+}
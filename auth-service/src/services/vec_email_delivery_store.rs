@@ -0,0 +1,91 @@
+use crate::domain::{DeliveryEvent, Email, EmailDeliveryStore, EmailDeliveryStoreError};
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+
+// In-memory email delivery store, used by tests and local development
+#[derive(Default)]
+pub struct VecEmailDeliveryStore {
+    events: RwLock<Vec<DeliveryEvent>>,
+}
+
+#[async_trait::async_trait]
+impl EmailDeliveryStore for VecEmailDeliveryStore {
+    async fn record_event(&self, event: DeliveryEvent) -> Result<(), EmailDeliveryStoreError> {
+        self.events.write().await.push(event);
+        Ok(())
+    }
+
+    async fn is_undeliverable(&self, recipient: &Email) -> Result<bool, EmailDeliveryStoreError> {
+        let recipient = recipient.as_ref().expose_secret().to_lowercase();
+        let events = self.events.read().await;
+        Ok(events
+            .iter()
+            .filter(|event| event.recipient == recipient && event.kind.is_definitive())
+            .max_by_key(|event| event.occurred_at)
+            .is_some_and(|event| event.kind.is_undeliverable()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::DeliveryEventKind;
+    use chrono::{Duration, Utc};
+    use secrecy::SecretString;
+
+    #[tokio::test]
+    async fn test_is_undeliverable() {
+        let store = VecEmailDeliveryStore::default();
+        let email = Email::parse(SecretString::from("Test@example.com")).unwrap();
+        let now = Utc::now();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(false));
+
+        let bounce = DeliveryEvent::new("test@example.com", DeliveryEventKind::HardBounce, now);
+        store.record_event(bounce).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(true));
+
+        // Soft bounces don't change the verdict, a later delivery does
+        let soft = DeliveryEvent::new(
+            "test@example.com",
+            DeliveryEventKind::SoftBounce,
+            now + Duration::seconds(1),
+        );
+        store.record_event(soft).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(true));
+        let delivered = DeliveryEvent::new(
+            "test@example.com",
+            DeliveryEventKind::Delivered,
+            now + Duration::seconds(2),
+        );
+        store.record_event(delivered).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_clear_suppression() {
+        let store = VecEmailDeliveryStore::default();
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let now = Utc::now();
+        let complaint =
+            DeliveryEvent::new("test@example.com", DeliveryEventKind::SpamComplaint, now);
+        store.record_event(complaint).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(true));
+
+        let cleared = DeliveryEvent::new(
+            "test@example.com",
+            DeliveryEventKind::SuppressionCleared,
+            now + Duration::seconds(1),
+        );
+        store.record_event(cleared).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(false));
+
+        // A new bounce suppresses the address again
+        let bounce = DeliveryEvent::new(
+            "test@example.com",
+            DeliveryEventKind::HardBounce,
+            now + Duration::seconds(2),
+        );
+        store.record_event(bounce).await.unwrap();
+        assert_eq!(store.is_undeliverable(&email).await, Ok(true));
+    }
+}
@@ -0,0 +1,47 @@
+use super::RequestId;
+use crate::AuditSinkType;
+use crate::domain::{AuditEvent, RequestMetadata};
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+// Collects the client IP, user agent and request ID of the current request.
+// Missing values are left empty rather than rejecting the request.
+impl<S> FromRequestParts<S> for RequestMetadata
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .map(|request_id| request_id.to_string());
+
+        Ok(RequestMetadata {
+            ip,
+            user_agent,
+            request_id,
+        })
+    }
+}
+
+// Records an audit event. A failing audit sink is logged but never fails the request.
+#[tracing::instrument(name = "Record audit event", skip_all)]
+pub async fn record_audit_event(audit_sink: &AuditSinkType, event: AuditEvent) {
+    if let Err(e) = audit_sink.record(event).await {
+        tracing::error!(error = ?e, "failed to record audit event");
+    }
+}
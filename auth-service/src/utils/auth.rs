@@ -1,52 +1,121 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
-use crate::BannedStoreType;
-use crate::domain::Email;
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use crate::domain::{AccountStatus, AuthAPIError, Email, User};
+use crate::settings::{AuthSettings, CookieSettings, CsrfSettings};
+use crate::{BannedStoreType, UserStoreType};
+use axum::http::HeaderMap;
+use axum::http::header::AUTHORIZATION;
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::Cookie;
 use chrono::Utc;
-use color_eyre::Result;
-use color_eyre::eyre::{Context, ContextCompat, eyre};
+use color_eyre::eyre::{Context, eyre};
+use color_eyre::{Report, Result};
 use jsonwebtoken::{DecodingKey, EncodingKey, Validation, decode, encode};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 // Create cookie with a new JWT auth token
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token))
+pub fn generate_auth_cookie(
+    user: &User,
+    settings: &AuthSettings,
+    cookie_settings: &CookieSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user, settings)?;
+    create_auth_cookie(token, settings.token_ttl, cookie_settings)
 }
 
-// Create cookie and set the value to the passed-in token string
+// Create cookie and set the value to the passed-in token string.
+// The cookie expires together with the token it holds.
 #[tracing::instrument(name = "Create Auth Cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
+fn create_auth_cookie(
+    token: String,
+    token_ttl: std::time::Duration,
+    settings: &CookieSettings,
+) -> Result<Cookie<'static>> {
+    let max_age = time::Duration::try_from(token_ttl).wrap_err("failed to convert token TTL")?;
+    let mut cookie = auth_cookie(token, settings);
+    cookie.set_max_age(max_age);
+    Ok(cookie)
+}
+
+// Cookie that clears the auth cookie, it must match the path and domain the cookie was set with
+pub fn auth_removal_cookie(settings: &CookieSettings) -> Cookie<'static> {
+    auth_cookie(String::new(), settings)
+}
+
+fn auth_cookie(value: String, settings: &CookieSettings) -> Cookie<'static> {
+    build_cookie(settings.name.clone(), value, settings)
+}
+
+// Cookie holding the CSRF token, scoped like the auth cookie it protects
+pub fn csrf_cookie(
+    token: String,
+    csrf_settings: &CsrfSettings,
+    settings: &CookieSettings,
+) -> Cookie<'static> {
+    build_cookie(csrf_settings.cookie_name.clone(), token, settings)
+}
+
+fn build_cookie(name: String, value: String, settings: &CookieSettings) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name, value))
         .path("/") // apple cookie to all URLs on the server
         .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+        .same_site(settings.same_site()) // "lax" sends the cookie with "same-site" requests, and with "cross-site" top-level navigations.
+        .secure(settings.secure) // only send the cookie over HTTPS
         .build();
-
+    if let Some(domain) = &settings.domain {
+        cookie.set_domain(domain.clone());
+    }
     cookie
 }
 
+// Token from an `Authorization: Bearer` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<SecretString> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(SecretString::from)
+}
+
+// Token of an authenticated request, from a Bearer header or else the auth cookie
+pub fn auth_token(
+    headers: &HeaderMap,
+    jar: &CookieJar,
+    settings: &CookieSettings,
+) -> Option<SecretString> {
+    bearer_token(headers).or_else(|| {
+        jar.get(&settings.name)
+            .map(|cookie| SecretString::from(cookie.value().to_owned()))
+    })
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
     UnexpectedError,
 }
 
-// This value determines how long the JWT auth token is valid for
-pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
-
 // Create JWT auth token
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+fn generate_auth_token(user: &User, settings: &AuthSettings) -> Result<String> {
+    // `token_ttl` determines how long the JWT auth token is valid for
+    let delta = chrono::Duration::from_std(settings.token_ttl)
+        .wrap_err("failed to create token TTL time delta")?;
+
+    let now = Utc::now();
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast issued-at time to usize")?;
 
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
+        .ok_or(eyre!("failed to add token TTL to current time"))?
         .timestamp();
 
     // Cast exp to a usize, which is what Claims expects
@@ -55,44 +124,99 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
+    let sub = user.email.as_ref().expose_secret().to_owned();
+
+    let claims = Claims {
+        sub,
+        exp,
+        iat,
+        ver: user.token_version,
+    };
 
-    let claims = Claims { sub, exp };
+    create_token(&claims, &settings.jwt_secret)
+}
 
-    create_token(&claims)
+// Reasons a token can fail validation. Everything except a disabled account
+// is reported to clients as an invalid token.
+#[derive(Debug, Error)]
+pub enum TokenValidationError {
+    #[error("Invalid token")]
+    InvalidToken(#[source] Report),
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("Account pending verification")]
+    AccountPendingVerification,
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
+impl From<TokenValidationError> for AuthAPIError {
+    fn from(e: TokenValidationError) -> Self {
+        match e {
+            TokenValidationError::InvalidToken(_) => AuthAPIError::InvalidToken,
+            TokenValidationError::AccountDisabled => AuthAPIError::AccountDisabled,
+            TokenValidationError::AccountPendingVerification => {
+                AuthAPIError::AccountPendingVerification
+            }
+        }
+    }
+}
+
+// Check if JWT auth token is valid by decoding it using the JWT secret.
+// Tokens issued before the user's last revocation, or belonging to a
+// disabled account, are rejected as well.
 #[tracing::instrument(name = "Validate Token", skip_all)]
 pub async fn validate_token(
     token: &SecretString,
+    settings: &AuthSettings,
     banned_token_store: BannedStoreType,
-) -> Result<Claims> {
-    match banned_token_store.read().await.contains_token(token).await {
+    user_store: UserStoreType,
+) -> Result<Claims, TokenValidationError> {
+    match banned_token_store.contains_token(token).await {
         Ok(value) => {
             if value {
-                return Err(eyre!("token is banned"));
+                return Err(TokenValidationError::InvalidToken(eyre!("token is banned")));
             }
         }
-        Err(e) => return Err(e.into()),
+        Err(e) => return Err(TokenValidationError::InvalidToken(e.into())),
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
     .wrap_err("failed to decode token")
+    .map_err(TokenValidationError::InvalidToken)?;
+
+    let email = Email::parse(SecretString::from(claims.sub.clone()))
+        .map_err(TokenValidationError::InvalidToken)?;
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|e| TokenValidationError::InvalidToken(e.into()))?;
+    match user.status {
+        AccountStatus::Active => {}
+        AccountStatus::Disabled => return Err(TokenValidationError::AccountDisabled),
+        AccountStatus::PendingVerification => {
+            return Err(TokenValidationError::AccountPendingVerification);
+        }
+    }
+    if claims.ver != user.token_version {
+        return Err(TokenValidationError::InvalidToken(eyre!(
+            "token has been revoked"
+        )));
+    }
+
+    Ok(claims)
 }
 
 // Create JWT auth token by encoding claims using the JWT secret
 #[tracing::instrument(name = "Create Token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+fn create_token(claims: &Claims, jwt_secret: &SecretString) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(jwt_secret.expose_secret().as_bytes()),
     )
     .wrap_err("failed to create token")
 }
@@ -101,53 +225,125 @@ fn create_token(claims: &Claims) -> Result<String> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    // The user's token version when the token was issued, see `User::token_version`
+    #[serde(default)]
+    pub ver: u32,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::HashSetBannedTokenStore;
+    use crate::utils::JWT_COOKIE_NAME;
+    use crate::{HashSetBannedTokenStore, HashmapUserStore, Password, User, UserStore};
+    use axum_extra::extract::cookie::SameSite;
     use secrecy::SecretString;
     use std::sync::Arc;
-    use tokio::sync::RwLock;
+    use std::time::Duration;
+
+    fn auth_settings() -> AuthSettings {
+        AuthSettings {
+            jwt_secret: SecretString::from("test-secret"),
+            token_ttl: Duration::from_secs(600),
+            two_fa_code_ttl: Duration::from_secs(600),
+        }
+    }
+
+    fn cookie_settings() -> CookieSettings {
+        CookieSettings {
+            name: JWT_COOKIE_NAME.to_owned(),
+            same_site: "lax".to_owned(),
+            domain: None,
+            secure: false,
+        }
+    }
+
+    fn user() -> User {
+        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
+        let password = Password::parse(SecretString::from("password")).unwrap();
+        User::new(email, password, false)
+    }
+
+    async fn user_store_with(user: &User) -> UserStoreType {
+        let user_store = HashmapUserStore::default();
+        user_store.add_user(user.clone()).await.unwrap();
+        Arc::new(user_store)
+    }
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&user(), &auth_settings(), &cookie_settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(600)));
     }
 
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
-        let cookie = create_auth_cookie(token.clone());
+        let cookie =
+            create_auth_cookie(token.clone(), Duration::from_secs(60), &cookie_settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.secure(), Some(false));
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(60)));
+    }
+
+    #[tokio::test]
+    async fn test_create_secure_host_cookie() {
+        let settings = CookieSettings {
+            name: "__Host-jwt".to_owned(),
+            same_site: "strict".to_owned(),
+            domain: None,
+            secure: true,
+        };
+        let cookie =
+            create_auth_cookie("test_token".to_owned(), Duration::from_secs(60), &settings)
+                .unwrap();
+        assert_eq!(cookie.name(), "__Host-jwt");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    }
+
+    #[tokio::test]
+    async fn test_auth_removal_cookie_keeps_domain() {
+        let settings = CookieSettings {
+            domain: Some("example.com".to_owned()),
+            ..cookie_settings()
+        };
+        let cookie = auth_removal_cookie(&settings);
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.path(), Some("/"));
     }
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&user(), &auth_settings()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(SecretString::from("test@example.com")).unwrap();
-        let token = SecretString::from(generate_auth_token(&email).unwrap());
-        let banned_token_store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store.clone())
-            .await
-            .unwrap();
+        let user = user();
+        let token = SecretString::from(generate_auth_token(&user, &auth_settings()).unwrap());
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = user_store_with(&user).await;
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await
+        .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -156,13 +352,106 @@ mod tests {
             .timestamp();
 
         assert!(result.exp > exp as usize);
+        // A NumericDate in seconds, like `exp`
+        assert!(result.iat <= Utc::now().timestamp() as usize);
+        assert_eq!(result.ver, 0);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = SecretString::from("invalid_token");
-        let banned_token_store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store.clone()).await;
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = Arc::new(HashmapUserStore::default());
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_token() {
+        let user = user();
+        let token = SecretString::from(generate_auth_token(&user, &auth_settings()).unwrap());
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = user_store_with(&user).await;
+        user_store.revoke_tokens(&user.email).await.unwrap();
+
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_right_after_revocation() {
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = user_store_with(&user()).await;
+        let email = user().email;
+        user_store.revoke_tokens(&email).await.unwrap();
+        // Within the same second as the revocation
+        let user = user_store.get_user(&email).await.unwrap();
+        let token = SecretString::from(generate_auth_token(&user, &auth_settings()).unwrap());
+
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_disabled_account() {
+        let user = user();
+        let token = SecretString::from(generate_auth_token(&user, &auth_settings()).unwrap());
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = user_store_with(&user).await;
+        user_store
+            .set_status(&user.email, AccountStatus::Disabled)
+            .await
+            .unwrap();
+
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await;
+        assert!(matches!(result, Err(TokenValidationError::AccountDisabled)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_account_pending_verification() {
+        let user = user();
+        let token = SecretString::from(generate_auth_token(&user, &auth_settings()).unwrap());
+        let banned_token_store = Arc::new(HashSetBannedTokenStore::default());
+        let user_store = user_store_with(&user).await;
+        user_store
+            .set_status(&user.email, AccountStatus::PendingVerification)
+            .await
+            .unwrap();
+
+        let result = validate_token(
+            &token,
+            &auth_settings(),
+            banned_token_store.clone(),
+            user_store,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(TokenValidationError::AccountPendingVerification)
+        ));
+    }
 }
@@ -1,72 +1,55 @@
-use dotenvy::dotenv;
-use secrecy::SecretString;
-use std::env as std_env;
-use std::sync::LazyLock;
-
+// Default name of the auth cookie, see `cookie.name` in the settings
 pub const JWT_COOKIE_NAME: &str = "jwt";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
-
-// Define lazily evaluated static. Lazy_static is needed because std_env::var is not a const function.
-pub static JWT_SECRET: LazyLock<SecretString> = LazyLock::new(set_token);
-pub static DATABASE_URL: LazyLock<SecretString> = LazyLock::new(get_db_url);
-pub static REDIS_HOST_NAME: LazyLock<String> = LazyLock::new(set_redis_host);
-pub static POSTMARK_AUTH_TOKEN: LazyLock<SecretString> = LazyLock::new(set_postmark_auth_token);
-
-fn get_db_url() -> SecretString {
-    dotenv().ok();
-    let url = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set");
-    if url.is_empty() {
-        panic!("DATABASE_URL must be set");
-    }
-    SecretString::from(url)
-}
-
-fn set_token() -> SecretString {
-    dotenv().ok(); // Load environment variables
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
-    }
-    SecretString::from(secret)
-}
-
-fn set_redis_host() -> String {
-    dotenv().ok();
-    std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
-}
-
-fn set_postmark_auth_token() -> SecretString {
-    dotenv().ok();
-    SecretString::from(
-        std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR).expect("POSTMARK_AUTH_TOKEN must be set."),
-    )
-}
 
+// Environment variables read before the settings file existed. They are still honoured and take
+// precedence over `configuration/base.toml`, the `APP_CONFIG_FILE` and `APP_*` variables.
 pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    // "postmark", "smtp", or a comma separated list of both in priority order for failover.
+    // "dev-mailbox" captures emails locally for `GET /dev/mailbox` instead of sending them.
+    pub const EMAIL_PROVIDER_ENV_VAR: &str = "EMAIL_PROVIDER";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    // One of "none", "starttls" or "tls"
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    // "twilio", or "none" to only log text messages
+    pub const SMS_PROVIDER_ENV_VAR: &str = "SMS_PROVIDER";
+    // E.164 number text messages are sent from
+    pub const SMS_SENDER_ENV_VAR: &str = "SMS_SENDER";
+    pub const TWILIO_ACCOUNT_SID_ENV_VAR: &str = "TWILIO_ACCOUNT_SID";
+    pub const TWILIO_AUTH_TOKEN_ENV_VAR: &str = "TWILIO_AUTH_TOKEN";
+    // Directory the dev mailbox writes to, messages are kept in memory when unset
+    pub const DEV_MAILBOX_DIR_ENV_VAR: &str = "DEV_MAILBOX_DIR";
+    // Shared secret Postmark sends in the `X-Postmark-Webhook-Token` header. Webhooks are
+    // rejected when it is unset.
+    pub const POSTMARK_WEBHOOK_TOKEN_ENV_VAR: &str = "POSTMARK_WEBHOOK_TOKEN";
 }
 
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+pub mod test {
     pub mod email_client {
         use std::time::Duration;
 
-        pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        // If you created your own Postmark account, make sure to use your email address!
-        pub const SENDER: &str = "bogdan@codeiron.io";
-        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+        pub const SENDER: &str = "test@email.com";
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+        pub const POSTMARK_WEBHOOK_TOKEN: &str = "test-webhook-token";
     }
-}
-
-pub mod test {
-    pub const APP_ADDRESS: &str = "127.0.0.1:0";
-    pub mod email_client {
+    pub mod sms_client {
         use std::time::Duration;
 
-        pub const SENDER: &str = "test@email.com";
+        pub const SENDER: &str = "+15005550006";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod email_outbox {
+        use std::time::Duration;
+
+        pub const MAX_ATTEMPTS: u32 = 3;
+        pub const BASE_DELAY: Duration = Duration::from_millis(50);
+        pub const MAX_DELAY: Duration = Duration::from_millis(200);
+        pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+    }
 }
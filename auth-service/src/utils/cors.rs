@@ -0,0 +1,134 @@
+use crate::settings::CorsSettings;
+use axum::http::header::HeaderName;
+use axum::http::{HeaderValue, Method, request};
+use color_eyre::Result;
+use color_eyre::eyre::{Context, eyre};
+use std::str::FromStr;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+// An allowed origin such as `https://app.example.com`, or `https://*.example.com` to allow
+// every subdomain of `example.com` (but not `example.com` itself).
+#[derive(Debug, Clone, PartialEq)]
+pub enum OriginPattern {
+    Exact(String),
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        let (scheme, authority) = pattern
+            .split_once("://")
+            .ok_or_else(|| eyre!("{pattern:?} must include a scheme"))?;
+        if !matches!(scheme, "http" | "https") {
+            return Err(eyre!("{pattern:?} must use http or https"));
+        }
+        if authority.is_empty() || authority.contains(['/', '?', '#']) {
+            return Err(eyre!(
+                "{pattern:?} must be a scheme and host without a path"
+            ));
+        }
+        match authority.strip_prefix("*.") {
+            Some(domain) if !domain.is_empty() && !domain.contains('*') => {
+                Ok(OriginPattern::Subdomains {
+                    scheme: format!("{scheme}://"),
+                    suffix: format!(".{domain}"),
+                })
+            }
+            None if !authority.contains('*') => Ok(OriginPattern::Exact(pattern)),
+            _ => Err(eyre!(
+                "{pattern:?} may only use a wildcard as its first label"
+            )),
+        }
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        match self {
+            OriginPattern::Exact(allowed) => origin == *allowed,
+            OriginPattern::Subdomains { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|authority| authority.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && !subdomain.starts_with('.')
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+        }
+    }
+}
+
+// Builds the CORS layer from settings already checked by `Settings::load`
+pub fn cors_layer(settings: &CorsSettings) -> Result<CorsLayer> {
+    let origins = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| OriginPattern::parse(origin))
+        .collect::<Result<Vec<_>>>()?;
+    let methods = settings
+        .allowed_methods
+        .iter()
+        .map(|method| Method::from_str(method).wrap_err("invalid CORS method"))
+        .collect::<Result<Vec<_>>>()?;
+    let headers = settings
+        .allowed_headers
+        .iter()
+        .map(|header| HeaderName::from_str(header).wrap_err("invalid CORS header"))
+        .collect::<Result<Vec<_>>>()?;
+
+    let allow_origin = AllowOrigin::predicate(move |origin: &HeaderValue, _: &request::Parts| {
+        origin
+            .to_str()
+            .is_ok_and(|origin| origins.iter().any(|pattern| pattern.matches(origin)))
+    });
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(settings.allow_credentials)
+        // Lets browser clients read the ID to include in bug reports
+        .expose_headers([HeaderName::from_static("x-request-id")])
+        .max_age(settings.max_age))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_origin() {
+        let pattern = OriginPattern::parse("http://localhost:8000").unwrap();
+        assert!(pattern.matches("http://localhost:8000"));
+        assert!(pattern.matches("HTTP://LOCALHOST:8000"));
+        assert!(!pattern.matches("http://localhost:8001"));
+        assert!(!pattern.matches("https://localhost:8000"));
+    }
+
+    #[test]
+    fn test_wildcard_subdomain_origin() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://eu.app.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("http://app.example.com"));
+        assert!(!pattern.matches("https://app.example.com:8443"));
+        assert!(!pattern.matches("https://evilexample.com"));
+        assert!(!pattern.matches("https://app.example.com.evil.io"));
+    }
+
+    #[test]
+    fn test_invalid_origin_patterns() {
+        for pattern in [
+            "142.93.34.195:8000",
+            "ftp://example.com",
+            "https://example.com/path",
+            "https://app.*.example.com",
+            "https://*.",
+            "*",
+        ] {
+            assert!(OriginPattern::parse(pattern).is_err(), "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use axum::extract::{MatchedPath, Request};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+// Every metric served by `GET /metrics`. They are process wide, so instances of the app
+// running side by side in tests add to the same series.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    logins: IntCounterVec,
+    two_fa_codes_issued: IntCounterVec,
+    two_fa_codes_verified: IntCounterVec,
+    password_hash_duration: HistogramVec,
+    email_send_failures: IntCounterVec,
+    store_operation_duration: HistogramVec,
+}
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels)
+                .expect("Invalid counter definition");
+            registry
+                .register(Box::new(counter.clone()))
+                .expect("Metric registered twice");
+            counter
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str]| {
+            let histogram = HistogramVec::new(HistogramOpts::new(name, help), labels)
+                .expect("Invalid histogram definition");
+            registry
+                .register(Box::new(histogram.clone()))
+                .expect("Metric registered twice");
+            histogram
+        };
+
+        Self {
+            http_requests: counter(
+                "http_requests_total",
+                "HTTP requests handled, by route and status",
+                &["method", "route", "status"],
+            ),
+            http_request_duration: histogram(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests, by route and status",
+                &["method", "route", "status"],
+            ),
+            logins: counter("logins_total", "Login attempts, by outcome", &["outcome"]),
+            two_fa_codes_issued: counter(
+                "two_fa_codes_issued_total",
+                "2FA codes sent, by channel",
+                &["channel"],
+            ),
+            two_fa_codes_verified: counter(
+                "two_fa_codes_verified_total",
+                "2FA codes checked, by outcome",
+                &["outcome"],
+            ),
+            password_hash_duration: histogram(
+                "password_hash_duration_seconds",
+                "Time spent hashing and verifying passwords with Argon2",
+                &["operation"],
+            ),
+            email_send_failures: counter(
+                "email_send_failures_total",
+                "Emails the provider failed to send, by where they were sent from",
+                &["source", "kind"],
+            ),
+            store_operation_duration: histogram(
+                "store_operation_duration_seconds",
+                "Time taken by data store operations",
+                &["store", "operation"],
+            ),
+            registry,
+        }
+    }
+
+    // Renders every metric in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+    }
+
+    // `outcome` is "success", "two_fa_required" or "failure"
+    pub fn record_login(&self, outcome: &str) {
+        self.logins.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_two_fa_code_issued(&self, channel: &str) {
+        self.two_fa_codes_issued.with_label_values(&[channel]).inc();
+    }
+
+    pub fn record_two_fa_code_verified(&self, success: bool) {
+        let outcome = if success { "success" } else { "failure" };
+        self.two_fa_codes_verified
+            .with_label_values(&[outcome])
+            .inc();
+    }
+
+    pub fn record_email_send_failure(&self, source: &str, permanent: bool) {
+        let kind = if permanent { "permanent" } else { "retryable" };
+        self.email_send_failures
+            .with_label_values(&[source, kind])
+            .inc();
+    }
+
+    // Observes the time until the returned timer is dropped
+    pub fn time_password_hash(&self, operation: &str) -> HistogramTimer {
+        self.password_hash_duration
+            .with_label_values(&[operation])
+            .start_timer()
+    }
+
+    // Observes the time until the returned timer is dropped
+    pub fn time_store_operation(&self, store: &str, operation: &str) -> HistogramTimer {
+        self.store_operation_duration
+            .with_label_values(&[store, operation])
+            .start_timer()
+    }
+
+    // Labelled with the route set by `set_route_labels`
+    pub fn record_http_response(&self, response: &Response, latency: Duration) {
+        let (method, route) = match response.extensions().get::<RouteLabels>() {
+            Some(labels) => (labels.method.as_str(), labels.route.as_str()),
+            None => ("UNKNOWN", UNMATCHED_ROUTE),
+        };
+        let status = response.status();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(latency.as_secs_f64());
+    }
+}
+
+// Requests no route matched, e.g. static assets, share one label so random paths can't
+// create new series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Clone)]
+struct RouteLabels {
+    method: Method,
+    route: String,
+}
+
+// Middleware copying the matched route template onto the response, where the `TraceLayer`
+// response hook can label metrics with it. It must run inside the `TraceLayer`.
+pub async fn set_route_labels(request: Request, next: Next) -> Response {
+    let labels = RouteLabels {
+        method: request.method().clone(),
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+            .to_owned(),
+    };
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(labels);
+    response
+}
@@ -1,8 +1,20 @@
+mod audit;
 mod auth;
 mod constants;
+mod cors;
+mod metrics;
+mod notification;
+mod shutdown;
+mod tls;
 mod tracing;
 
+pub use audit::*;
 pub use auth::*;
 pub use constants::*;
+pub use cors::*;
+pub use metrics::*;
+pub use notification::*;
+pub use shutdown::*;
 pub use test::*;
+pub use tls::*;
 pub use tracing::*;
@@ -0,0 +1,58 @@
+use crate::domain::{
+    Device, DeviceSighting, Email, OutboxEmail, RequestMetadata, SecurityNotification,
+};
+use crate::email_templates::{Locale, security_notification_email};
+use crate::{AppState, EmailOutboxType};
+use uuid::Uuid;
+
+// Queues a security notification in the email outbox, so a slow or failing
+// email provider never fails the request that triggered it.
+// The idempotency key is generated here rather than taken from the request, since
+// a client could otherwise reuse another request's key to suppress the email.
+#[tracing::instrument(name = "Queue security notification", skip_all)]
+pub async fn queue_security_notification(
+    email_outbox: &EmailOutboxType,
+    recipient: &Email,
+    notification: SecurityNotification,
+) {
+    let message = match security_notification_email(&notification, Locale::default()) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::error!(error = ?e, "failed to render security notification");
+            return;
+        }
+    };
+    let email = OutboxEmail {
+        idempotency_key: format!("{}:{}", notification.as_str(), Uuid::now_v7()),
+        recipient: recipient.clone(),
+        message,
+        expires_at: None,
+    };
+    if let Err(e) = email_outbox.enqueue(email).await {
+        tracing::error!(error = ?e, "failed to queue security notification");
+    }
+}
+
+// Remembers the device a user just signed in from and warns them if it has never been seen before.
+// The very first device of an account is remembered silently.
+#[tracing::instrument(name = "Check known device", skip_all)]
+pub async fn notify_if_new_device(state: &AppState, email: &Email, metadata: &RequestMetadata) {
+    let device = Device::from(metadata);
+    let sighting = state
+        .known_device_store
+        .remember_device(email, device.clone())
+        .await;
+
+    match sighting {
+        Ok(DeviceSighting::New) => {
+            queue_security_notification(
+                &state.email_outbox,
+                email,
+                SecurityNotification::NewDevice(device),
+            )
+            .await
+        }
+        Ok(DeviceSighting::Known | DeviceSighting::First) => {}
+        Err(e) => tracing::error!(error = ?e, "failed to remember device"),
+    }
+}
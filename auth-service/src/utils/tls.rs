@@ -0,0 +1,294 @@
+use crate::settings::TlsSettings;
+use axum::Router;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::http::header::LOCATION;
+use axum::response::{IntoResponse, Response};
+use axum::serve::Listener;
+use color_eyre::Result;
+use color_eyre::eyre::{Context, eyre};
+use rustls::ServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+// Slow or stalled handshakes are dropped instead of holding a connection slot
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Completed handshakes waiting for the server to pick them up
+const ACCEPT_BACKLOG: usize = 128;
+
+// Serves HTTPS on top of a TCP listener. Handshakes run in their own tasks so one slow client
+// can't hold up the others, and the certificate is reloaded when its files change on disk.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    streams: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, settings: &TlsSettings) -> Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let cert_path = PathBuf::from(
+            settings
+                .cert_path
+                .as_ref()
+                .ok_or_else(|| eyre!("tls.cert_path is not set"))?,
+        );
+        let key_path = PathBuf::from(
+            settings
+                .key_path
+                .as_ref()
+                .ok_or_else(|| eyre!("tls.key_path is not set"))?,
+        );
+        let acceptor = Arc::new(RwLock::new(load_acceptor(&cert_path, &key_path)?));
+
+        let reloader = CertificateReloader {
+            acceptor: Arc::downgrade(&acceptor),
+            modified: modified_times(&cert_path, &key_path),
+            cert_path,
+            key_path,
+        };
+        tokio::spawn(reloader.run(settings.reload_interval));
+
+        let (sender, streams) = mpsc::channel(ACCEPT_BACKLOG);
+        tokio::spawn(accept_connections(listener, acceptor, sender));
+        Ok(Self {
+            local_addr,
+            streams,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.streams.recv().await {
+            Some(connection) => connection,
+            // The accept loop only stops once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    acceptor: Arc<RwLock<TlsAcceptor>>,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let (stream, address) = tokio::select! {
+            _ = sender.closed() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(connection) => connection,
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to accept TCP connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+        };
+        let acceptor = acceptor.read().expect("TLS acceptor lock poisoned").clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, address)).await;
+                }
+                Ok(Err(e)) => tracing::debug!(error = %e, %address, "TLS handshake failed"),
+                Err(_) => tracing::debug!(%address, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+struct CertificateReloader {
+    acceptor: Weak<RwLock<TlsAcceptor>>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    modified: Option<(SystemTime, SystemTime)>,
+}
+
+impl CertificateReloader {
+    // Polls the certificate and key files, stopping once the listener is gone
+    async fn run(mut self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(acceptor) = self.acceptor.upgrade() else {
+                return;
+            };
+            let modified = modified_times(&self.cert_path, &self.key_path);
+            if modified == self.modified {
+                continue;
+            }
+            // Remember the attempt even if it fails, a half-written pair is retried once the
+            // other file changes too
+            self.modified = modified;
+            match load_acceptor(&self.cert_path, &self.key_path) {
+                Ok(reloaded) => {
+                    *acceptor.write().expect("TLS acceptor lock poisoned") = reloaded;
+                    tracing::info!("reloaded TLS certificate");
+                }
+                Err(e) => tracing::warn!(error = ?e, "failed to reload TLS certificate"),
+            }
+        }
+    }
+}
+
+fn modified_times(cert_path: &Path, key_path: &Path) -> Option<(SystemTime, SystemTime)> {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((modified(cert_path)?, modified(key_path)?))
+}
+
+fn load_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .wrap_err_with(|| format!("failed to read certificates from {}", cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .wrap_err_with(|| format!("failed to read private key from {}", key_path.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .wrap_err("failed to configure TLS protocol versions")?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .wrap_err("certificate and private key don't match")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+// Sends every plain HTTP request to the same path on the public HTTPS URL
+pub fn https_redirect_router(public_url: &str) -> Router {
+    let public_url = public_url.trim_end_matches('/').to_owned();
+    Router::new().fallback(move |request: Request| {
+        let public_url = public_url.clone();
+        async move { https_redirect(&public_url, request) }
+    })
+}
+
+fn https_redirect(public_url: &str, request: Request) -> Response {
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    (
+        StatusCode::PERMANENT_REDIRECT,
+        [(LOCATION, format!("{public_url}{path}"))],
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{CertifiedKey, generate_simple_self_signed};
+
+    fn write_certificate(dir: &Path, name: &str) -> (PathBuf, PathBuf, Vec<u8>) {
+        let CertifiedKey { cert, key_pair } =
+            generate_simple_self_signed(vec![name.to_owned()]).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, cert.pem()).unwrap();
+        std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+        (cert_path, key_path, cert.der().to_vec())
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tls-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn peer_certificate(address: SocketAddr) -> Vec<u8> {
+        let response = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .tls_info(true)
+            .build()
+            .unwrap()
+            .get(format!("https://{address}/"))
+            .send()
+            .await
+            .unwrap();
+        response
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|info| info.peer_certificate())
+            .expect("No peer certificate")
+            .to_vec()
+    }
+
+    async fn serve(settings: &TlsSettings) -> SocketAddr {
+        let listener =
+            TlsListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap(), settings).unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, Router::new()).into_future());
+        address
+    }
+
+    #[test]
+    fn test_load_acceptor_rejects_missing_files() {
+        let dir = temp_dir();
+        let result = load_acceptor(&dir.join("cert.pem"), &dir.join("key.pem"));
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serves_and_reloads_certificate() {
+        let dir = temp_dir();
+        let (cert_path, key_path, first) = write_certificate(&dir, "first.localhost");
+        let settings = TlsSettings {
+            enabled: true,
+            cert_path: Some(cert_path.to_string_lossy().into_owned()),
+            key_path: Some(key_path.to_string_lossy().into_owned()),
+            reload_interval: Duration::from_millis(50),
+            redirect_http_port: None,
+        };
+        let address = serve(&settings).await;
+        assert_eq!(peer_certificate(address).await, first);
+
+        // Make sure the new files get a different modification time
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let (_, _, second) = write_certificate(&dir, "second.localhost");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(peer_certificate(address).await, second);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redirects_to_public_url() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = https_redirect_router("https://auth.example.com/");
+        tokio::spawn(axum::serve(listener, router).into_future());
+
+        let response = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .post(format!("http://{address}/login?next=%2Fapp"))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://auth.example.com/login?next=%2Fapp"
+        );
+    }
+}
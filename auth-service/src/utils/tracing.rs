@@ -1,45 +1,204 @@
+use crate::settings::TelemetrySettings;
+use crate::utils::metrics;
 use axum::body::Body;
 use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
 use axum::response::Response;
 use color_eyre::Result;
+use opentelemetry::Context;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
 use std::time::Duration;
-use tracing::{Level, Span};
+use tracing::{Level, Span, Subscriber};
 use tracing_error::ErrorLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::Layer;
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{EnvFilter, fmt};
+use uuid::Uuid;
 
-pub fn init_tracing() -> Result<()> {
-    // Create a formatting layer for tracing output with a compact format
-    let fmt_layer = fmt::layer().compact();
+// Returned by `init_tracing`, call `shutdown` before exiting to export spans still buffered
+pub struct TracingGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl TracingGuard {
+    pub fn shutdown(self) {
+        if let Some(tracer_provider) = self.tracer_provider
+            && let Err(e) = tracer_provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {e}");
+        }
+    }
+}
+
+pub fn init_tracing(settings: &TelemetrySettings) -> Result<TracingGuard> {
+    // Create a formatting layer for tracing output, either compact or JSON
+    let (compact_layer, json_layer) = match settings.json_logs() {
+        true => (None, Some(json_layer(std::io::stdout))),
+        false => (Some(fmt::layer().compact()), None),
+    };
 
     // Create a filter layer to control the verbosity of logs
     // Try to get the filter configuration from the environment variables
     // If it fails, default to the "info" log level
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    // Export spans to an OpenTelemetry collector when an endpoint is configured
+    let tracer_provider = match &settings.otlp_endpoint {
+        Some(endpoint) => Some(tracer_provider(endpoint, settings)?),
+        None => None,
+    };
+    let otel_layer = tracer_provider.as_ref().map(|tracer_provider| {
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("auth-service"))
+    });
+
     // Build the tracing subscriber registry with the formatting layer,
     // the filter layer, and the error layer for enhanced error reporting
     tracing_subscriber::registry()
         .with(filter_layer) // Add the filter layer to control log verbosity
-        .with(fmt_layer) // Add the formatting layer for compact log output
+        .with(compact_layer) // Add the formatting layer for compact log output
+        .with(json_layer) // Or the one for JSON log output
+        .with(otel_layer) // Add the OpenTelemetry layer when exporting traces
         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
         .init(); // Initialize the tracing subscriber
 
-    Ok(())
+    Ok(TracingGuard { tracer_provider })
+}
+
+// One JSON object per event, with the fields of the request span (e.g. `request_id`) inlined
+fn json_layer<S, W>(make_writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(make_writer)
+}
+
+fn tracer_provider(endpoint: &str, settings: &TelemetrySettings) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .with_timeout(settings.export_timeout)
+        .build()?;
+    let resource = Resource::builder()
+        .with_service_name(settings.service_name.clone())
+        .build();
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}
+
+// W3C trace context, read from `traceparent` on incoming requests and written on outgoing ones
+fn trace_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+// Headers carrying the current span's trace context to another service. Empty unless spans
+// are being exported, as there is no trace to continue otherwise.
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    TraceContextPropagator::new().inject_context(
+        &Span::current().context(),
+        &mut HeaderInjector(&mut headers),
+    );
+    headers
+}
+
+// Unique ID assigned to every incoming request and stored in its extensions,
+// so the tracing span and the handlers (e.g. for audit events) share the same value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestId(pub String);
+
+// Carries the request ID in both directions, so client reports can be matched with our logs
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+impl RequestId {
+    // The caller's `X-Request-Id` when it looks like an ID, else the trace ID of a request
+    // carrying a valid `traceparent` header, so audit events and logs can be matched with
+    // the distributed trace. Otherwise a new UUID.
+    fn for_request(headers: &HeaderMap) -> Self {
+        let incoming = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| Self::is_valid(id));
+        if let Some(id) = incoming {
+            return RequestId(id.to_owned());
+        }
+        let context = trace_context(headers);
+        let span_context = context.span().span_context().clone();
+        match span_context.is_valid() {
+            true => RequestId(span_context.trace_id().to_string()),
+            false => RequestId(Uuid::now_v7().to_string()),
+        }
+    }
+
+    // IDs end up in logs and the audit trail, so only short printable tokens are accepted
+    fn is_valid(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= MAX_REQUEST_ID_LENGTH
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Middleware assigning a `RequestId` to the request and echoing it in the response.
+// It must wrap the `TraceLayer` so the ID is already present when the request span is created.
+pub async fn set_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = RequestId::for_request(request.headers());
+    let header_value = HeaderValue::from_str(&request_id.0).ok();
+    request.extensions_mut().insert(request_id);
+    let mut response = next.run(request).await;
+    if let Some(header_value) = header_value {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, header_value);
+    }
+    response
 }
 
 // Creates a new tracing span with a unique request ID for each incoming request.
 // This helps in tracking and correlating logs for individual requests.
+// A `traceparent` header makes the span a child of the caller's span.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::now_v7();
-    tracing::span!(
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .cloned()
+        .unwrap_or_else(|| RequestId::for_request(request.headers()));
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
         uri = tracing::field::display(request.uri()),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+    );
+    // Only fails when spans aren't exported, leaving nothing to link
+    let _ = span.set_parent(trace_context(request.headers()));
+    span
 }
 
 // Logs an event indicating the start of a request.
@@ -49,7 +208,9 @@ pub fn on_request(_request: &Request<Body>, _span: &Span) {
 
 // Logs an event indicating the end of a request, including its latency and status code.
 // If the status code indicates an error (4xx or 5xx), it logs at the ERROR level.
+// Also records the request metrics.
 pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
+    metrics().record_http_response(response, latency);
     let status = response.status();
     let status_code = status.as_u16();
     let status_code_class = status_code / 100;
@@ -73,3 +234,114 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(traceparent: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", HeaderValue::from_str(traceparent).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_request_id_uses_trace_id_from_traceparent() {
+        let request_id = RequestId::for_request(&headers(TRACEPARENT));
+        assert_eq!(request_id, RequestId(TRACE_ID.to_owned()));
+    }
+
+    #[test]
+    fn test_request_id_prefers_incoming_request_id() {
+        let mut headers = headers(TRACEPARENT);
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("client-42"));
+        assert_eq!(
+            RequestId::for_request(&headers),
+            RequestId("client-42".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_request_id_ignores_invalid_incoming_request_id() {
+        let too_long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for id in ["", "has spaces", "<script>", too_long.as_str()] {
+            let mut headers = HeaderMap::new();
+            headers.insert(REQUEST_ID_HEADER, HeaderValue::from_str(id).unwrap());
+            let request_id = RequestId::for_request(&headers);
+            assert!(Uuid::parse_str(&request_id.0).is_ok(), "{id}");
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_logs_include_request_span_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(json_layer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("[REQUEST]", request_id = "client-42");
+            span.in_scope(|| tracing::info!(status = 200, "[REQUEST END]"));
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["message"], "[REQUEST END]");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["span"]["request_id"], "client-42");
+    }
+
+    #[test]
+    fn test_request_id_ignores_invalid_traceparent() {
+        for traceparent in [
+            "not-a-traceparent",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        ] {
+            let request_id = RequestId::for_request(&headers(traceparent));
+            assert!(Uuid::parse_str(&request_id.0).is_ok(), "{traceparent}");
+        }
+        let request_id = RequestId::for_request(&HeaderMap::new());
+        assert!(Uuid::parse_str(&request_id.0).is_ok());
+    }
+
+    #[test]
+    fn test_trace_context_headers_continue_the_incoming_trace() {
+        let tracer_provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("test")));
+
+        let headers = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            span.set_parent(trace_context(&headers(TRACEPARENT)))
+                .unwrap();
+            span.in_scope(trace_context_headers)
+        });
+
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{TRACE_ID}-")));
+        assert_ne!(traceparent, TRACEPARENT);
+    }
+
+    #[test]
+    fn test_trace_context_headers_are_empty_without_exporter() {
+        assert!(trace_context_headers().is_empty());
+    }
+}
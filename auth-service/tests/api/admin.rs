@@ -0,0 +1,246 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::AccountStatus;
+use auth_service::routes::{ListUsersResponse, UserResponse};
+use auth_service::utils::JWT_COOKIE_NAME;
+use reqwest::StatusCode;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_admin_users(&[]).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+    let login_response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(login_response.status(), StatusCode::OK);
+
+    let response = app.get_admin_users(&[]).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_search_users_with_pagination() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+    let first = format!("first-{}", get_random_email());
+    let second = format!("second-{}", get_random_email());
+    signup(&app, &first, false).await;
+    signup(&app, &second, true).await;
+
+    let response = app
+        .get_admin_users(&[("page", "1"), ("perPage", "2")])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    // Users are ordered by email, and the admin's uuid-based address sorts first
+    assert_eq!(body.total, 3);
+    assert_eq!(body.users.len(), 2);
+    assert_eq!(body.users[1].email, first);
+
+    let response = app
+        .get_admin_users(&[("page", "2"), ("perPage", "2")])
+        .await;
+    let body = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(body.users.len(), 1);
+    assert_eq!(body.users[0].email, second);
+
+    let response = app.get_admin_users(&[("search", "second-")]).await;
+    let body = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(body.total, 1);
+    assert_eq!(body.users[0].email, second);
+    assert!(body.users[0].requires_2fa);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_user_not_found() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let response = app.get_admin_user(&get_random_email()).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_and_enable_user() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    let response = app
+        .post_admin_user_action(&email, "disable", &json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user = response.json::<UserResponse>().await.unwrap();
+    assert_eq!(user.status, AccountStatus::Disabled);
+
+    let login_body = json!({ "email": email, "password": "password123" });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .post_admin_user_action(&email, "enable", &json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user = response.json::<UserResponse>().await.unwrap();
+    assert_eq!(user.status, AccountStatus::Active);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_toggle_requires_2fa() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    let response = app
+        .post_admin_user_action(&email, "requires-2fa", &json!({ "requires2FA": true }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.get_admin_user(&email).await;
+    let user = response.json::<UserResponse>().await.unwrap();
+    assert!(user.requires_2fa);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_notify_user_when_2fa_disabled() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+    signup(&app, &email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_admin_user_action(&email, "requires-2fa", &json!({ "requires2FA": false }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let emails = app.wait_for_emails(1).await;
+    assert_eq!(emails[0]["To"], email);
+    assert_eq!(
+        emails[0]["Subject"],
+        "Two-factor authentication was disabled"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_password() {
+    let mut app = TestApp::new().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    let response = app
+        .post_admin_user_action(
+            &email,
+            "reset-password",
+            &json!({ "password": "new_password123" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user = response.json::<UserResponse>().await.unwrap();
+    assert!(user.tokens_revoked_at.is_some());
+
+    let old_login = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(old_login.status(), StatusCode::UNAUTHORIZED);
+
+    // Logging in right after the reset gives a token that is not revoked with the old ones
+    let new_login = app
+        .post_login(&json!({ "email": email, "password": "new_password123" }))
+        .await;
+    assert_eq!(new_login.status(), StatusCode::OK);
+    let new_token = new_login
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let response = app.post_verify_token(&json!({ "token": new_token })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let emails = app.wait_for_emails(1).await;
+    assert_eq!(emails[0]["To"], email);
+    assert_eq!(emails[0]["Subject"], "Your password was changed");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_all_tokens() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+    let login_response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    let user_token = login_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    app.login_as_admin().await;
+    let response = app
+        .post_admin_user_action(&email, "revoke-tokens", &json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
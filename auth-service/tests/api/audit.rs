@@ -0,0 +1,96 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::AuditEventKind;
+use auth_service::routes::ListAuditEventsResponse;
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+
+    let response = app.get_admin_audit_events(&[]).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_login_events_with_request_metadata() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": "wrong_password" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.login_as_admin().await;
+    let response = app.get_admin_audit_events(&[("email", &email)]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    // Events are returned newest first
+    let kinds: Vec<_> = body.events.iter().map(|event| event.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AuditEventKind::LoginSucceeded,
+            AuditEventKind::LoginFailed,
+            AuditEventKind::Signup
+        ]
+    );
+    assert_eq!(body.total, 3);
+    let event = &body.events[0];
+    assert_eq!(event.ip.as_deref(), Some("127.0.0.1"));
+    assert!(event.request_id.is_some());
+    assert_eq!(
+        body.events[1].detail.as_deref(),
+        Some("incorrect credentials")
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_filter_audit_events_by_kind() {
+    let mut app = TestApp::new().await;
+    let response = app.post_verify_token(&json!({ "token": "invalid" })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    app.login_as_admin().await;
+    let response = app
+        .get_admin_audit_events(&[("kind", "token_rejected")])
+        .await;
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    assert_eq!(body.total, 1);
+    assert_eq!(body.events[0].kind, AuditEventKind::TokenRejected);
+    assert!(body.events[0].email.is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_unknown_event_kind() {
+    let mut app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let response = app.get_admin_audit_events(&[("kind", "unknown")]).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
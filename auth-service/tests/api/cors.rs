@@ -0,0 +1,86 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn should_allow_preflight_from_allowed_origin() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .preflight("/login", "http://localhost:8000", "POST")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "http://localhost:8000"
+    );
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-max-age"], "3600");
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"));
+    let allowed_headers = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed_headers.contains("content-type"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_allow_preflight_from_denied_origin() {
+    let mut app = TestApp::new().await;
+
+    for origin in ["http://evil.example.com", "http://localhost:8001"] {
+        let response = app.preflight("/login", origin, "POST").await;
+
+        assert!(
+            !response
+                .headers()
+                .contains_key("access-control-allow-origin"),
+            "{origin} should be denied"
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_preflight_from_wildcard_subdomain() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.cors.allowed_origins = vec!["https://*.example.com".to_owned()];
+        settings.cors.max_age = std::time::Duration::from_secs(600);
+    })
+    .await;
+
+    let response = app
+        .preflight("/verify-token", "https://app.example.com", "POST")
+        .await;
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    assert_eq!(response.headers()["access-control-max-age"], "600");
+
+    for origin in ["https://example.com", "https://app.example.com.evil.io"] {
+        let response = app.preflight("/verify-token", origin, "POST").await;
+        assert!(
+            !response
+                .headers()
+                .contains_key("access-control-allow-origin"),
+            "{origin} should be denied"
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_allow_unconfigured_method() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .preflight("/login", "http://localhost:8000", "DELETE")
+        .await;
+
+    let methods = response.headers()["access-control-allow-methods"]
+        .to_str()
+        .unwrap();
+    assert!(!methods.contains("DELETE"));
+    app.clean_up().await;
+}
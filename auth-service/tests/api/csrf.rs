@@ -0,0 +1,109 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::ErrorResponse;
+use auth_service::routes::{CSRF_TOKEN_HEADER, CsrfTokenResponse};
+use auth_service::utils::JWT_COOKIE_NAME;
+use reqwest::StatusCode;
+use serde_json::json;
+
+// Signs up and logs in a user without 2FA, returning the JWT
+async fn login(app: &TestApp) -> String {
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_same_csrf_token_for_existing_cookie() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/csrf-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response
+        .json::<CsrfTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to CsrfTokenResponse");
+    assert_eq!(body.csrf_token, app.csrf_token);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_cookie_authenticated_request_has_no_csrf_token() {
+    let mut app = TestApp::new().await;
+    login(&app).await;
+
+    let response = app
+        .client_without_csrf_token()
+        .post(format!("{}/logout", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Invalid CSRF token");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_csrf_token_does_not_match() {
+    let mut app = TestApp::new().await;
+    login(&app).await;
+
+    let response = app
+        .client_without_csrf_token()
+        .post(format!("{}/two-fa-channel", &app.address))
+        .header(CSRF_TOKEN_HEADER, "not-the-csrf-token")
+        .json(&json!({ "twoFAChannel": "email" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_cookie_authenticated_request_with_csrf_token() {
+    let mut app = TestApp::new().await;
+    login(&app).await;
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_bypass_csrf_check_for_bearer_token() {
+    let mut app = TestApp::new().await;
+    let token = login(&app).await;
+
+    let response = app
+        .client_without_csrf_token()
+        .post(format!("{}/logout", &app.address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    app.clean_up().await;
+}
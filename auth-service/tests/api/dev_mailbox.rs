@@ -0,0 +1,66 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::CapturedEmail;
+use auth_service::routes::TwoFactorAuthResponse;
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn should_return_404_if_dev_mailbox_disabled() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_mailbox("text/html").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_complete_2fa_flow_from_dev_mailbox() {
+    let mut app = TestApp::with_dev_mailbox().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": true });
+    app.post_signup(&signup_body).await;
+
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let response = app.get_dev_mailbox("application/json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let emails = response
+        .json::<Vec<CapturedEmail>>()
+        .await
+        .expect("Could not deserialize response body to captured emails");
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].recipient, email);
+    assert_eq!(emails[0].subject, "Your verification code");
+
+    // The code is the only six-digit run in the text body
+    let code = emails[0]
+        .text_body
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|word| word.len() == 6)
+        .expect("No 2FA code in captured email")
+        .to_owned();
+    let verify_body =
+        json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page = app.get_dev_mailbox("text/html").await.text().await.unwrap();
+    assert!(page.contains(&email));
+    let response = app
+        .http_client
+        .get(format!("{}/dev/mailbox/{}", &app.address, emails[0].id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), emails[0].html_body);
+    app.clean_up().await;
+}
@@ -0,0 +1,98 @@
+use crate::helpers::TestApp;
+use auth_service::routes::{LivenessResponse, ReadinessResponse};
+use reqwest::StatusCode;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn should_return_200_when_alive() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health("live").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<LivenessResponse>().await.unwrap();
+    assert_eq!(body.status, "alive");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_when_dependencies_are_up() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health("ready").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, "ready");
+    assert_eq!(
+        body.checks.keys().collect::<Vec<_>>(),
+        ["postgres", "redis"]
+    );
+    for check in body.checks.values() {
+        assert_eq!(check.status, "up");
+        assert!(check.critical);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_503_when_postgres_is_down() {
+    let mut app = TestApp::new().await;
+    // Drops the test database, so the pool can no longer connect
+    app.clean_up().await;
+
+    let response = app.get_health("ready").await;
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = response.text().await.unwrap();
+    // The reason is logged, not shown to anonymous callers
+    assert!(!body.contains("error"), "{body}");
+    let body = serde_json::from_str::<ReadinessResponse>(&body).unwrap();
+    assert_eq!(body.status, "unavailable");
+    assert_eq!(body.checks["postgres"].status, "down");
+    assert_eq!(body.checks["redis"].status, "up");
+}
+
+#[tokio::test]
+async fn should_report_email_provider_when_enabled() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.health.check_email_provider = true;
+    })
+    .await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .and(header("X-Postmark-Server-Token", "auth_token"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.get_health("ready").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.checks["email"].status, "up");
+    assert!(!body.checks["email"].critical);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stay_ready_when_email_provider_is_down() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.health.check_email_provider = true;
+    })
+    .await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.get_health("ready").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, "ready");
+    assert_eq!(body.checks["email"].status, "down");
+    app.clean_up().await;
+}
@@ -1,83 +1,289 @@
-use auth_service::utils::test::APP_ADDRESS;
-use auth_service::utils::{DATABASE_URL, REDIS_HOST_NAME, test};
+use auth_service::routes::{CSRF_TOKEN_HEADER, CsrfTokenResponse, POSTMARK_WEBHOOK_TOKEN_HEADER};
+use auth_service::settings::Settings;
+use auth_service::utils::test;
 use auth_service::{
-    AppState, Application, BannedStoreType, Email, PostgresUserStore, PostmarkEmailClient,
-    RedisBannedTokenStore, RedisTwoFACodeStore, TwoFACodeStoreType, get_postgres_pool,
-    get_redis_client,
+    AppState, Application, AuditSinkType, BannedStoreType, DevMailboxEmailClient, Email,
+    EmailClientType, EmailOutboxType, EmailOutboxWorker, EmailProviderHealthCheck, Password,
+    PhoneNumber, PhoneVerificationStoreType, PostgresAuditSink, PostgresBannedTokenStore,
+    PostgresEmailDeliveryStore, PostgresEmailOutbox, PostgresHealthCheck, PostgresKnownDeviceStore,
+    PostgresPhoneVerificationStore, PostgresTwoFACodeStore, PostgresUserStore, PostmarkEmailClient,
+    RedisBannedTokenStore, RedisConnection, RedisHealthCheck, RedisTwoFACodeStore, TwilioSmsClient,
+    TwoFACodeStoreType, User, UserStoreType, get_postgres_pool,
 };
-use reqwest::Client;
 use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, ClientBuilder};
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use wiremock::MockServer;
 
 pub struct TestApp {
     pub address: String,
+    pub http_redirect_address: Option<String>,
+    pub settings: Arc<Settings>,
     pub cookie_jar: Arc<Jar>,
+    pub csrf_token: String,
+    pub user_store: UserStoreType,
+    #[allow(dead_code)]
     pub banned_token_store: BannedStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub phone_verification_store: PhoneVerificationStoreType,
+    // The multiplexed connection the Redis stores share
+    pub redis_conn: RedisConnection,
+    // The database created for this test
+    pub pg_pool: PgPool,
+    #[allow(dead_code)]
+    pub audit_sink: AuditSinkType,
+    pub email_outbox: EmailOutboxType,
     pub http_client: Client,
     pub email_server: MockServer,
+    pub sms_server: MockServer,
     pub db_name: String,
     pub clean_up_called: bool,
+    shutdown: CancellationToken,
+    server: Option<JoinHandle<Result<(), std::io::Error>>>,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
-        let (db_name, pg_pool) = Self::configure_postgresql().await;
-        let redis_pool = Arc::new(RwLock::new(Self::configure_redis().await));
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store =
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_pool.clone())));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_pool.clone())));
+        Self::spawn(Self::configure_settings(), None).await
+    }
+
+    // Adjusts the test settings before the app is built
+    pub async fn with_settings(configure: impl FnOnce(&mut Settings)) -> Self {
+        let mut settings = Self::configure_settings();
+        configure(&mut settings);
+        Self::spawn(settings, None).await
+    }
+
+    // Captures outgoing email in an in-memory dev mailbox instead of the mock Postmark server
+    pub async fn with_dev_mailbox() -> Self {
+        Self::spawn(
+            Self::configure_settings(),
+            Some(Arc::new(DevMailboxEmailClient::in_memory())),
+        )
+        .await
+    }
+
+    async fn spawn(settings: Settings, dev_mailbox: Option<Arc<DevMailboxEmailClient>>) -> Self {
+        let settings = Arc::new(settings);
+        let (db_name, pg_pool) = Self::configure_postgresql(&settings).await;
+        let redis_conn = Self::configure_redis(&settings).await;
+        let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+        let audit_sink: AuditSinkType = Arc::new(PostgresAuditSink::new(pg_pool.clone()));
+        let known_device_store = Arc::new(PostgresKnownDeviceStore::new(pg_pool.clone()));
+        let email_delivery_store = Arc::new(PostgresEmailDeliveryStore::new(pg_pool.clone()));
+        let email_outbox: EmailOutboxType = Arc::new(PostgresEmailOutbox::new(pg_pool.clone()));
+        let phone_verification_store: PhoneVerificationStoreType =
+            Arc::new(PostgresPhoneVerificationStore::new(
+                pg_pool.clone(),
+                settings.auth.two_fa_code_ttl,
+                settings.sms.verification_max_sends,
+                settings.sms.verification_send_window,
+            ));
+        let use_redis = settings.token_store.backend == "redis";
+        let (banned_token_store, two_fa_code_store): (BannedStoreType, TwoFACodeStoreType) =
+            match use_redis {
+                true => (
+                    Arc::new(RedisBannedTokenStore::new(
+                        redis_conn.clone(),
+                        settings.auth.token_ttl,
+                    )),
+                    Arc::new(RedisTwoFACodeStore::new(
+                        redis_conn.clone(),
+                        settings.auth.two_fa_code_ttl,
+                    )),
+                ),
+                false => (
+                    Arc::new(PostgresBannedTokenStore::new(
+                        pg_pool.clone(),
+                        settings.auth.token_ttl,
+                    )),
+                    Arc::new(PostgresTwoFACodeStore::new(
+                        pg_pool.clone(),
+                        settings.auth.two_fa_code_ttl,
+                    )),
+                ),
+            };
         let email_server = MockServer::start().await;
+        let sms_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(Self::configure_postmark_email_client(base_url));
+        let email_client: EmailClientType = match &dev_mailbox {
+            Some(dev_mailbox) => dev_mailbox.clone(),
+            None => Arc::new(Self::configure_postmark_email_client(base_url)),
+        };
         // let email_client = Arc::new(MockEmailClient {});
         let app_state = AppState::new(
+            settings.clone(),
             user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client.clone(),
+            audit_sink.clone(),
+            known_device_store,
+            email_outbox.clone(),
+            email_delivery_store,
+            Arc::new(Self::configure_twilio_sms_client(
+                sms_server.uri(),
+                &settings,
+            )),
+            phone_verification_store.clone(),
+        )
+        .with_dev_mailbox(dev_mailbox)
+        .with_health_check(
+            "postgres",
+            true,
+            Arc::new(PostgresHealthCheck::new(pg_pool.clone())),
         );
-        let app = Application::build(app_state, APP_ADDRESS)
+        let app_state = match use_redis {
+            true => app_state.with_health_check(
+                "redis",
+                true,
+                Arc::new(RedisHealthCheck::new(redis_conn.clone())),
+            ),
+            false => app_state,
+        };
+        let app_state = match settings.health.check_email_provider {
+            true => app_state.with_health_check(
+                "email",
+                false,
+                Arc::new(EmailProviderHealthCheck::new(email_client.clone())),
+            ),
+            false => app_state,
+        };
+        let email_outbox_worker = EmailOutboxWorker::new(
+            email_outbox.clone(),
+            email_client,
+            settings.email_outbox.retry_policy(),
+            settings.email_outbox.poll_interval,
+        );
+        let shutdown = CancellationToken::new();
+        tokio::spawn(email_outbox_worker.run(shutdown.clone()));
+        let app = Application::build(app_state)
             .await
             .expect("Failed to build app");
 
-        let address = format!("http://{}", app.address.clone());
+        let scheme = if settings.tls.enabled {
+            "https"
+        } else {
+            "http"
+        };
+        let address = format!("{}://{}", scheme, app.address.clone());
+        let http_redirect_address = app
+            .http_redirect_address
+            .as_ref()
+            .map(|address| format!("http://{}", address));
 
         // Run the auth service in a separate async task
         // to avoid blocking the main test thread.
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let server = tokio::spawn(app.run(shutdown.clone()));
 
         let cookie_jar = Arc::new(Jar::default());
-        let http_client = Client::builder()
-            .cookie_provider(cookie_jar.clone())
+        // Fetch a CSRF token like a browser front end would, and send it with every request
+        let csrf_token = Self::fetch_csrf_token(&address, cookie_jar.clone()).await;
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            CSRF_TOKEN_HEADER,
+            HeaderValue::from_str(&csrf_token).expect("Invalid CSRF token"),
+        );
+        let http_client = Self::client_builder(cookie_jar.clone())
+            .default_headers(default_headers)
             .build()
             .unwrap(); // Create a Reqwest http client instance
 
         // Create a new ` TestApp ` instance and return it
         Self {
             address,
+            http_redirect_address,
+            settings,
             cookie_jar,
+            csrf_token,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            phone_verification_store,
+            redis_conn,
+            pg_pool,
+            audit_sink,
+            email_outbox,
             http_client,
             email_server,
+            sms_server,
             db_name,
             clean_up_called: false,
+            shutdown,
+            server: Some(server),
         }
     }
 
+    // Tests serving HTTPS use self-signed certificates
+    fn client_builder(cookie_jar: Arc<Jar>) -> ClientBuilder {
+        Client::builder()
+            .cookie_provider(cookie_jar)
+            .danger_accept_invalid_certs(true)
+    }
+
+    async fn fetch_csrf_token(address: &str, cookie_jar: Arc<Jar>) -> String {
+        Self::client_builder(cookie_jar)
+            .build()
+            .unwrap()
+            .get(format!("{}/csrf-token", address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json::<CsrfTokenResponse>()
+            .await
+            .expect("Could not deserialize response body to CsrfTokenResponse")
+            .csrf_token
+    }
+
+    // Client sharing the cookie jar but sending no CSRF token header
+    pub fn client_without_csrf_token(&self) -> Client {
+        Self::client_builder(self.cookie_jar.clone())
+            .build()
+            .unwrap()
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health(&self, probe: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/{}", &self.address, probe))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // CORS preflight request as a browser would send it before a cross-origin request
+    pub async fn preflight(&self, route: &str, origin: &str, method: &str) -> reqwest::Response {
+        self.http_client
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}{}", &self.address, route),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .header("Access-Control-Request-Headers", "content-type")
             .send()
             .await
             .expect("Failed to execute request.")
@@ -88,7 +294,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -100,7 +306,58 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login_with_user_agent<Body>(
+        &self,
+        body: &Body,
+        user_agent: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login", &self.address))
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_postmark_webhook<Body>(&self, body: &Body, token: &str) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webhooks/postmark", &self.address))
+            .header(POSTMARK_WEBHOOK_TOKEN_HEADER, token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self, accept: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/mailbox", &self.address))
+            .header(reqwest::header::ACCEPT, accept)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_json<Body>(&self, route: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}{}", &self.address, route))
             .json(body)
             .send()
             .await
@@ -109,7 +366,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -120,7 +377,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
             .json(body)
             .send()
             .await
@@ -139,21 +396,128 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
-    async fn configure_postgresql() -> (String, PgPool) {
-        let postgresql_conn_url = DATABASE_URL.to_owned();
+    // Seeds an admin account directly in the user store and logs in as it,
+    // so the cookie jar holds an admin JWT for the `/admin` routes.
+    // Security notifications are sent from a background task, so poll the mock email server
+    // until the expected number of emails has arrived and return their JSON bodies.
+    pub async fn wait_for_emails(&self, expected: usize) -> Vec<serde_json::Value> {
+        for _ in 0..50 {
+            let requests = self
+                .email_server
+                .received_requests()
+                .await
+                .unwrap_or_default();
+            if requests.len() >= expected {
+                return requests
+                    .iter()
+                    .map(|request| request.body_json().expect("Email body is not JSON"))
+                    .collect();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("Timed out waiting for {expected} emails");
+    }
+
+    pub async fn login_as_admin(&self) -> Email {
+        let email = Email::parse(SecretString::from(get_random_email())).unwrap();
+        let password = Password::parse(SecretString::from("admin_password")).unwrap();
+        let mut admin = User::new(email.clone(), password, false);
+        admin.is_admin = true;
+        self.user_store
+            .add_user(admin)
+            .await
+            .expect("Failed to add admin user");
+
+        let response = self
+            .post_login(&serde_json::json!({
+                "email": email.as_ref().expose_secret(),
+                "password": "admin_password",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        email
+    }
+
+    pub async fn get_admin_users(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_audit_events(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/audit-events", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_user_action<Body>(
+        &self,
+        email: &str,
+        action: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!(
+                "{}/admin/users/{}/{}",
+                &self.address, email, action
+            ))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Settings from the environment, adjusted so tests bind a random port and retry quickly
+    fn configure_settings() -> Settings {
+        let mut settings = Settings::load().expect("Failed to load settings");
+        settings.application.host = "127.0.0.1".to_owned();
+        settings.application.port = 0;
+        settings.email.postmark.webhook_token = Some(SecretString::from(
+            test::email_client::POSTMARK_WEBHOOK_TOKEN,
+        ));
+        settings.email_outbox.max_attempts = test::email_outbox::MAX_ATTEMPTS;
+        settings.email_outbox.base_delay = test::email_outbox::BASE_DELAY;
+        settings.email_outbox.max_delay = test::email_outbox::MAX_DELAY;
+        settings.email_outbox.poll_interval = test::email_outbox::POLL_INTERVAL;
+        settings.sms.timeout = test::sms_client::TIMEOUT;
+        settings
+    }
+
+    async fn configure_postgresql(settings: &Settings) -> (String, PgPool) {
+        let postgresql_conn_url = settings.database.url.clone();
 
         // We are creating a new database for each test case, and we need to ensure each database has a unique name!
         let db_name = Uuid::now_v7().to_string();
 
-        Self::configure_database(&postgresql_conn_url.expose_secret(), &db_name).await;
+        Self::configure_database(postgresql_conn_url.expose_secret(), &db_name).await;
 
         let postgresql_conn_url_with_db =
             format!("{}/{}", postgresql_conn_url.expose_secret(), db_name);
 
         // Create a new connection pool and return it
-        let pg_pool = get_postgres_pool(&SecretString::from(postgresql_conn_url_with_db))
-            .await
-            .expect("Failed to create Postgres connection pool!");
+        let pg_pool = get_postgres_pool(
+            &SecretString::from(postgresql_conn_url_with_db),
+            settings.database.max_connections,
+        )
+        .await
+        .expect("Failed to create Postgres connection pool!");
         (db_name, pg_pool)
     }
 
@@ -185,10 +549,9 @@ impl TestApp {
             .expect("Failed to migrate the database");
     }
 
-    async fn configure_redis() -> redis::Connection {
-        get_redis_client(REDIS_HOST_NAME.to_owned())
-            .expect("Failed to get Redis client")
-            .get_connection()
+    async fn configure_redis(settings: &Settings) -> RedisConnection {
+        RedisConnection::connect(&settings.redis)
+            .await
             .expect("Failed to get Redis connection")
     }
 
@@ -205,8 +568,25 @@ impl TestApp {
         PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
     }
 
-    async fn delete_database(db_name: &str) {
-        let postgresql_conn_url: String = DATABASE_URL.expose_secret().to_owned();
+    fn configure_twilio_sms_client(base_url: String, settings: &Settings) -> TwilioSmsClient {
+        let sender = PhoneNumber::parse(SecretString::from(test::sms_client::SENDER)).unwrap();
+
+        let http_client = Client::builder()
+            .timeout(settings.sms.timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        TwilioSmsClient::new(
+            base_url,
+            "test_account_sid".to_owned(),
+            SecretString::from("auth_token"),
+            sender,
+            http_client,
+        )
+    }
+
+    async fn delete_database(database_url: &SecretString, db_name: &str) {
+        let postgresql_conn_url: String = database_url.expose_secret().to_owned();
 
         let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
             .expect("Failed to parse PostgreSQL connection string");
@@ -238,8 +618,22 @@ impl TestApp {
             .await
             .expect("Failed to drop the database.");
     }
+    // Starts a graceful shutdown as SIGTERM would, without waiting for it to finish
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    // Waits for the server to stop after `shutdown`
+    pub async fn wait_for_server(&mut self) -> Result<(), std::io::Error> {
+        self.server
+            .take()
+            .expect("Server already awaited")
+            .await
+            .expect("Server task panicked")
+    }
+
     pub async fn clean_up(&mut self) {
-        Self::delete_database(&self.db_name).await;
+        Self::delete_database(&self.settings.database.url, &self.db_name).await;
         self.clean_up_called = true
     }
 }
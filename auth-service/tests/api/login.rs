@@ -1,7 +1,7 @@
 use crate::helpers::{TestApp, get_random_email};
-use auth_service::Email;
 use auth_service::routes::TwoFactorAuthResponse;
 use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{AccountStatus, Email, ErrorResponse, OutboxStatus};
 use reqwest::StatusCode;
 use secrecy::{ExposeSecret, SecretString};
 use serde_json::json;
@@ -149,8 +149,6 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     //assert that `json_body.login_attempt_id` is stored inside `app.two_fa_code_store`
     let two_fa_tuple = app
         .two_fa_code_store
-        .read()
-        .await
         .get_code(&email)
         .await
         .expect("Could not get code");
@@ -161,3 +159,318 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     );
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_queue_2fa_email_if_email_provider_fails() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": true });
+    app.post_signup(&signup_body).await;
+
+    // The synchronous attempt fails, the outbox worker's retry succeeds
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({ "email": email, "password": "password" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let emails = app.wait_for_emails(2).await;
+    assert_eq!(emails[0], emails[1]);
+    let idempotency_key = format!("two-fa:{}", json_body.login_attempt_id);
+    let mut entry = None;
+    for _ in 0..50 {
+        entry = app.email_outbox.get_entry(&idempotency_key).await.unwrap();
+        if entry
+            .as_ref()
+            .is_some_and(|entry| entry.status == OutboxStatus::Delivered)
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let entry = entry.expect("No outbox entry found");
+    assert_eq!(entry.status, OutboxStatus::Delivered);
+    assert!(entry.email.expires_at.is_some());
+    // The delivered email no longer holds the code
+    assert!(entry.email.message.html_body.is_empty());
+    assert!(entry.email.message.text_body.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_queue_2fa_email_if_provider_rejects_it() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": true });
+    app.post_signup(&signup_body).await;
+
+    // Postmark's "inactive recipient" error can't be fixed by retrying
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to recipient(s) that have been marked as inactive."
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({ "email": email, "password": "password" });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_outbox")
+        .fetch_one(&app.pg_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 0);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_account_disabled() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let email = Email::parse(SecretString::from(email)).unwrap();
+    let signup_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password",
+        "requires2FA": true
+    });
+    let signup_result = app.post_signup(&signup_payload).await;
+    assert_eq!(signup_result.status(), StatusCode::CREATED);
+
+    app.user_store
+        .set_status(&email, AccountStatus::Disabled)
+        .await
+        .unwrap();
+
+    // No 2FA email may be sent for a disabled account
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password"
+    });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Account disabled"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_account_pending_verification() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let email = Email::parse(SecretString::from(email)).unwrap();
+    let signup_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password",
+        "requires2FA": false
+    });
+    let signup_result = app.post_signup(&signup_payload).await;
+    assert_eq!(signup_result.status(), StatusCode::CREATED);
+
+    app.user_store
+        .set_status(&email, AccountStatus::PendingVerification)
+        .await
+        .unwrap();
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password"
+    });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .cookies()
+            .all(|cookie| cookie.name() != JWT_COOKIE_NAME)
+    );
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Account pending verification"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_notify_user_when_signing_in_from_new_device() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // The first device and repeat sign-ins from it are not reported
+    let login_body = json!({ "email": email, "password": "password" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post_login_with_user_agent(&login_body, "new-device/1.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let emails = app.wait_for_emails(1).await;
+    assert_eq!(emails[0]["To"], email);
+    assert_eq!(emails[0]["Subject"], "New sign-in to your account");
+    assert!(
+        emails[0]["TextBody"]
+            .as_str()
+            .unwrap()
+            .contains("new-device/1.0")
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_notify_each_user_when_request_ids_collide() {
+    let mut app = TestApp::new().await;
+    let emails = [get_random_email(), get_random_email()];
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    for email in &emails {
+        let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+        app.post_signup(&signup_body).await;
+        let login_body = json!({ "email": email, "password": "password" });
+        app.post_login(&login_body).await;
+    }
+
+    // The request ID is chosen by the client, so one user must not be able to
+    // reuse it to suppress another user's notification
+    for email in &emails {
+        let login_body = json!({ "email": email, "password": "password" });
+        let response = app
+            .http_client
+            .post(format!("{}/login", &app.address))
+            .header(reqwest::header::USER_AGENT, "new-device/1.0")
+            .header("X-Request-Id", "same-request-id")
+            .json(&login_body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let received = app.wait_for_emails(2).await;
+    for email in &emails {
+        assert!(received.iter().any(|message| message["To"] == *email));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_if_new_device_notification_fails() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({ "email": email, "password": "password" });
+    app.post_login(&login_body).await;
+    let response = app
+        .post_login_with_user_agent(&login_body, "new-device/1.0")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_expire_auth_cookie_with_token() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(app.settings.auth.token_ttl),
+        "Max-Age should match the token TTL"
+    );
+    assert!(auth_cookie.http_only());
+    assert!(!auth_cookie.secure());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_set_configured_cookie_attributes() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.cookie.name = "__Host-jwt".to_owned();
+        settings.cookie.same_site = "strict".to_owned();
+        settings.cookie.secure = true;
+    })
+    .await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == "__Host-jwt")
+        .expect("No auth cookie found");
+    assert!(auth_cookie.secure());
+    assert!(auth_cookie.same_site_strict());
+    assert_eq!(auth_cookie.path(), Some("/"));
+    assert_eq!(auth_cookie.domain(), None);
+    app.clean_up().await;
+}
@@ -1,6 +1,7 @@
 use crate::helpers::{TestApp, get_random_email};
-use auth_service::Email;
+use auth_service::routes::{CSRF_TOKEN_HEADER, CsrfTokenResponse};
 use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{AccountStatus, Email};
 use reqwest::{StatusCode, Url};
 use secrecy::{ExposeSecret, SecretString};
 use serde_json::json;
@@ -88,3 +89,128 @@ async fn should_return_401_if_invalid_token() {
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_clear_configured_cookie() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.cookie.name = "session".to_owned();
+    })
+    .await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    let login_body = json!({ "email": email, "password": "password" });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), StatusCode::OK);
+
+    let logout_response = app.post_logout().await;
+
+    assert_eq!(logout_response.status(), StatusCode::OK);
+    let removal_cookie = logout_response
+        .cookies()
+        .find(|cookie| cookie.name() == "session")
+        .expect("No removal cookie found");
+    assert!(removal_cookie.value().is_empty());
+    assert_eq!(removal_cookie.path(), Some("/"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_clear_cookie_if_account_disabled() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    let login_body = json!({ "email": email, "password": "password" });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), StatusCode::OK);
+    app.user_store
+        .set_status(
+            &Email::parse(SecretString::from(email)).unwrap(),
+            AccountStatus::Disabled,
+        )
+        .await
+        .unwrap();
+
+    let logout_response = app.post_logout().await;
+
+    assert_eq!(logout_response.status(), StatusCode::FORBIDDEN);
+    let removal_cookie = logout_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No removal cookie found");
+    assert!(removal_cookie.value().is_empty());
+    app.clean_up().await;
+}
+
+// Mirrors the app service front end, which logs out from another origin by first
+// fetching a CSRF token and then echoing it in the logout request
+#[tokio::test]
+async fn should_logout_from_app_origin_with_csrf_token() {
+    let mut app = TestApp::new().await;
+    let origin = "http://localhost:8000";
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    let login_body = json!({ "email": email, "password": "password" });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), StatusCode::OK);
+
+    // A browser only tracks cookies, so use a client without the default CSRF header
+    let client = app.client_without_csrf_token();
+    let preflight = client
+        .request(reqwest::Method::OPTIONS, format!("{}/logout", &app.address))
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "x-csrf-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(preflight.status(), StatusCode::OK);
+    assert_eq!(preflight.headers()["access-control-allow-origin"], origin);
+    assert_eq!(
+        preflight.headers()["access-control-allow-credentials"],
+        "true"
+    );
+    let allowed_headers = preflight.headers()["access-control-allow-headers"]
+        .to_str()
+        .unwrap();
+    assert!(allowed_headers.contains("x-csrf-token"));
+
+    let csrf_response = client
+        .get(format!("{}/csrf-token", &app.address))
+        .header("Origin", origin)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(
+        csrf_response.headers()["access-control-allow-origin"],
+        origin
+    );
+    let csrf_token = csrf_response
+        .json::<CsrfTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to CsrfTokenResponse")
+        .csrf_token;
+
+    let response = client
+        .post(format!("{}/logout", &app.address))
+        .header("Origin", origin)
+        .header(CSRF_TOKEN_HEADER, csrf_token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], origin);
+    assert_eq!(
+        response.headers()["access-control-allow-credentials"],
+        "true"
+    );
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+    app.clean_up().await;
+}
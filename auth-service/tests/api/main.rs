@@ -1,7 +1,21 @@
+mod admin;
+mod audit;
+mod cors;
+mod csrf;
+mod dev_mailbox;
+mod health;
 mod helpers;
 mod login;
 mod logout;
+mod metrics;
+mod phone_number;
+mod redis_connection;
+mod request_id;
 mod root;
+mod shutdown;
 mod signup;
+mod tls;
+mod token_stores;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
@@ -0,0 +1,102 @@
+use crate::helpers::{TestApp, get_random_email};
+use reqwest::StatusCode;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+
+// Metrics are shared by every app in the test process, so only check that series exist
+async fn scrape(app: &TestApp) -> String {
+    let response = app.get_metrics().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    response.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn should_count_requests_by_route_and_status() {
+    let mut app = TestApp::new().await;
+    app.get_health("live").await;
+    app.http_client
+        .get(format!("{}/admin/users/someone@example.com", &app.address))
+        .send()
+        .await
+        .unwrap();
+    app.http_client
+        .get(format!(
+            "{}/no-such-page-{}",
+            &app.address,
+            uuid::Uuid::now_v7()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    let metrics = scrape(&app).await;
+
+    assert!(
+        metrics.contains(r#"http_requests_total{method="GET",route="/health/live",status="200"}"#)
+    );
+    assert!(metrics.contains(
+        r#"http_request_duration_seconds_count{method="GET",route="/admin/users/{email}",status="400"}"#
+    ));
+    assert!(
+        metrics.contains(r#"http_requests_total{method="GET",route="unmatched",status="404"}"#)
+    );
+    assert!(!metrics.contains("no-such-page"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_logins_password_hashing_and_store_latency() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.post_signup(&signup_body).await;
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    app.post_login(&json!({ "email": email, "password": "wrong-password" }))
+        .await;
+
+    let metrics = scrape(&app).await;
+
+    assert!(metrics.contains(r#"logins_total{outcome="success"}"#));
+    assert!(metrics.contains(r#"logins_total{outcome="failure"}"#));
+    assert!(metrics.contains(r#"password_hash_duration_seconds_count{operation="hash"}"#));
+    assert!(metrics.contains(r#"password_hash_duration_seconds_count{operation="verify"}"#));
+    assert!(metrics.contains(
+        r#"store_operation_duration_seconds_count{operation="add_user",store="postgres_user_store"}"#
+    ));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_two_fa_codes_issued_and_verified() {
+    let mut app = TestApp::with_dev_mailbox().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": true });
+    app.post_signup(&signup_body).await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let verify_body = json!({
+        "email": email,
+        "loginAttemptId": uuid::Uuid::now_v7().to_string(),
+        "2FACode": "000000",
+    });
+    app.post_verify_2fa(&verify_body).await;
+
+    let metrics = scrape(&app).await;
+
+    assert!(metrics.contains(r#"logins_total{outcome="two_fa_required"}"#));
+    assert!(metrics.contains(r#"two_fa_codes_issued_total{channel="email"}"#));
+    assert!(metrics.contains(r#"two_fa_codes_verified_total{outcome="failure"}"#));
+    assert!(metrics.contains(
+        r#"store_operation_duration_seconds_count{operation="add_code",store="redis_two_fa_code_store"}"#
+    ));
+    app.clean_up().await;
+}
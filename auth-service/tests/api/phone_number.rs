@@ -0,0 +1,231 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::routes::{SetPhoneNumberResponse, TwoFactorAuthResponse};
+use auth_service::{Email, PhoneNumber, TwoFAChannel};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+const PHONE_NUMBER: &str = "+14155552671";
+
+// Signs up a user without 2FA and logs in, leaving the auth cookie in the jar
+async fn signup_and_login(app: &TestApp) -> Email {
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(
+        app.post_signup(&signup_body).await.status(),
+        StatusCode::CREATED
+    );
+    let login_body = json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login_body).await.status(), StatusCode::OK);
+    Email::parse(SecretString::from(email)).unwrap()
+}
+
+async fn mount_sms_response(app: &TestApp, status: u16) {
+    Mock::given(path_regex(r"^/2010-04-01/Accounts/.+/Messages\.json$"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(status))
+        .mount(&app.sms_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_sms_channel_chosen_without_verified_phone() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_json("/two-fa-channel", &json!({ "twoFAChannel": "sms" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_phone_verification_code_is_incorrect() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app).await;
+    mount_sms_response(&app, 201).await;
+
+    let response = app
+        .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<SetPhoneNumberResponse>().await.unwrap();
+
+    let verify_body = json!({ "loginAttemptId": body.login_attempt_id, "2FACode": "000000" });
+    let response = app.post_json("/phone-number/verify", &verify_body).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_by_sms_after_phone_verified() {
+    let mut app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    mount_sms_response(&app, 201).await;
+
+    let response = app
+        .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<SetPhoneNumberResponse>().await.unwrap();
+    let verification = app.phone_verification_store.get_code(&email).await.unwrap();
+    let verify_body = json!({
+        "loginAttemptId": body.login_attempt_id,
+        "2FACode": verification.code.as_ref().expose_secret()
+    });
+    let response = app.post_json("/phone-number/verify", &verify_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .post_json("/two-fa-channel", &json!({ "twoFAChannel": "sms" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.user_store.set_requires_2fa(&email, true).await.unwrap();
+    let login_body = json!({ "email": email.as_ref().expose_secret(), "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let body = response.json::<TwoFactorAuthResponse>().await.unwrap();
+    assert_eq!(body.channel, TwoFAChannel::Sms);
+
+    // The verification text and the login code, nothing by email
+    let (_, code) = app.two_fa_code_store.get_code(&email).await.unwrap();
+    let texts = app.sms_server.received_requests().await.unwrap();
+    assert_eq!(texts.len(), 2);
+    let text = String::from_utf8(texts[1].body.clone()).unwrap();
+    assert!(text.contains(code.as_ref().expose_secret()));
+    assert!(
+        app.email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_fall_back_to_email_if_sms_fails() {
+    let mut app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    {
+        let user_store = &app.user_store;
+        let phone_number = PhoneNumber::parse(SecretString::from(PHONE_NUMBER)).unwrap();
+        user_store
+            .set_phone_number(&email, phone_number)
+            .await
+            .unwrap();
+        user_store.mark_phone_verified(&email).await.unwrap();
+        user_store
+            .set_two_fa_channel(&email, TwoFAChannel::Sms)
+            .await
+            .unwrap();
+        user_store.set_requires_2fa(&email, true).await.unwrap();
+    }
+    mount_sms_response(&app, 500).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({ "email": email.as_ref().expose_secret(), "password": "password123" });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let body = response.json::<TwoFactorAuthResponse>().await.unwrap();
+    assert_eq!(body.channel, TwoFAChannel::Email);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_login_and_phone_verification_codes_apart() {
+    let mut app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    mount_sms_response(&app, 201).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.user_store.set_requires_2fa(&email, true).await.unwrap();
+    let login_body = json!({ "email": email.as_ref().expose_secret(), "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let login_code = app.two_fa_code_store.get_code(&email).await.unwrap();
+
+    // Starting a phone verification leaves the pending login code alone
+    let response = app
+        .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        app.two_fa_code_store.get_code(&email).await,
+        Ok(login_code.clone())
+    );
+
+    // and the emailed login code can't verify the phone number
+    let (login_attempt_id, code) = login_code;
+    let verify_body = json!({
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let response = app.post_json("/phone-number/verify", &verify_body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_verify_a_number_the_code_was_not_sent_to() {
+    let mut app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    mount_sms_response(&app, 201).await;
+    let response = app
+        .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.json::<SetPhoneNumberResponse>().await.unwrap();
+    let verification = app.phone_verification_store.get_code(&email).await.unwrap();
+
+    let other_number = PhoneNumber::parse(SecretString::from("+14155552672")).unwrap();
+    app.user_store
+        .set_phone_number(&email, other_number)
+        .await
+        .unwrap();
+    let verify_body = json!({
+        "loginAttemptId": body.login_attempt_id,
+        "2FACode": verification.code.as_ref().expose_secret()
+    });
+    let response = app.post_json("/phone-number/verify", &verify_body).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_too_many_verification_codes_sent() {
+    let mut app = TestApp::with_settings(|settings| settings.sms.verification_max_sends = 2).await;
+    signup_and_login(&app).await;
+    mount_sms_response(&app, 201).await;
+
+    for _ in 0..2 {
+        let response = app
+            .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app
+        .post_json("/phone-number", &json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let texts = app.sms_server.received_requests().await.unwrap();
+    assert_eq!(texts.len(), 2);
+    app.clean_up().await;
+}
@@ -0,0 +1,152 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::get_redis_client;
+use auth_service::utils::JWT_COOKIE_NAME;
+use redis::AsyncCommands;
+use reqwest::StatusCode;
+use secrecy::SecretString;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+const CONCURRENT_REQUESTS: usize = 200;
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(
+        app.post_signup(&signup_body).await.status(),
+        StatusCode::CREATED
+    );
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie returned")
+        .value()
+        .to_owned()
+}
+
+// Every `/verify-token` request checks the banned token store, so these all query Redis at
+// the same time over the shared connection
+#[tokio::test]
+async fn should_check_banned_tokens_concurrently() {
+    let mut app = TestApp::new().await;
+    let valid_token = signup_and_login(&app).await;
+    let banned_token = signup_and_login(&app).await;
+    app.banned_token_store
+        .add_token(&SecretString::from(banned_token.clone()))
+        .await
+        .unwrap();
+
+    let started = Instant::now();
+    let mut requests = JoinSet::new();
+    for i in 0..CONCURRENT_REQUESTS {
+        let (token, expected) = match i % 2 {
+            0 => (valid_token.clone(), StatusCode::OK),
+            _ => (banned_token.clone(), StatusCode::UNAUTHORIZED),
+        };
+        let request = app
+            .http_client
+            .post(format!("{}/verify-token", &app.address))
+            .json(&json!({ "token": token }))
+            .send();
+        requests.spawn(async move { (request.await, expected) });
+    }
+    for (response, expected) in requests.join_all().await {
+        assert_eq!(
+            response.expect("Failed to execute request.").status(),
+            expected
+        );
+    }
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(10),
+        "{CONCURRENT_REQUESTS} token checks took {elapsed:?}"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reconnect_after_redis_drops_the_connection() {
+    let mut app = TestApp::new().await;
+    let token = SecretString::from("token-checked-across-a-reconnect".to_owned());
+    let client_id: i64 = redis::cmd("CLIENT")
+        .arg("ID")
+        .query_async(&mut app.redis_conn.clone())
+        .await
+        .unwrap();
+
+    // Close the stores' connection from the server side, as a Redis restart would
+    let mut admin = get_redis_client(&app.settings.redis.url())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let _: i64 = redis::cmd("CLIENT")
+        .arg("KILL")
+        .arg("ID")
+        .arg(client_id)
+        .query_async(&mut admin)
+        .await
+        .unwrap();
+
+    // The command that finds the connection closed may fail, later ones use a new connection
+    let mut result = Err(());
+    for _ in 0..20 {
+        result = app
+            .banned_token_store
+            .contains_token(&token)
+            .await
+            .map_err(|_| ());
+        if result.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(result, Ok(false));
+
+    let new_client_id: i64 = redis::cmd("CLIENT")
+        .arg("ID")
+        .query_async(&mut app.redis_conn.clone())
+        .await
+        .unwrap();
+    assert_ne!(new_client_id, client_id);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_use_database_index_from_redis_url() {
+    let mut app = TestApp::with_settings(|settings| {
+        let url = format!("redis://{}/3", settings.redis.host_name);
+        settings.redis.url = Some(SecretString::from(url));
+    })
+    .await;
+    let token = signup_and_login(&app).await;
+    assert_eq!(app.post_logout().await.status(), StatusCode::OK);
+    assert_eq!(
+        app.post_verify_token(&json!({ "token": token }))
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let key = format!("banned_token:{token}");
+    let mut db_3 = get_redis_client(&app.settings.redis.url())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let mut db_0 = get_redis_client(&SecretString::from(format!(
+        "redis://{}/",
+        app.settings.redis.host_name
+    )))
+    .unwrap()
+    .get_multiplexed_async_connection()
+    .await
+    .unwrap();
+    assert!(db_3.exists::<_, bool>(&key).await.unwrap());
+    assert!(!db_0.exists::<_, bool>(&key).await.unwrap());
+    app.clean_up().await;
+}
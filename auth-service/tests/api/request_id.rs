@@ -0,0 +1,117 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::AuditEventKind;
+use auth_service::routes::ListAuditEventsResponse;
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn should_return_generated_request_id() {
+    let mut app = TestApp::new().await;
+
+    let first = app.get_health("live").await;
+    let second = app.get_health("live").await;
+
+    let first = first.headers()["x-request-id"].to_str().unwrap().to_owned();
+    let second = second.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(&first).is_ok());
+    assert_ne!(first, second);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_echo_incoming_request_id_and_record_it() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+
+    let response = app
+        .http_client
+        .post(format!("{}/signup", &app.address))
+        .header("X-Request-Id", "client-report-42")
+        .json(&signup_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.headers()["x-request-id"], "client-report-42");
+
+    app.login_as_admin().await;
+    let body = app
+        .get_admin_audit_events(&[("email", &email)])
+        .await
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+    assert_eq!(body.events[0].kind, AuditEventKind::Signup);
+    assert_eq!(
+        body.events[0].request_id.as_deref(),
+        Some("client-report-42")
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_use_trace_id_from_traceparent_as_request_id() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    app.http_client
+        .post(format!("{}/signup", &app.address))
+        .header(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .json(&signup_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    app.login_as_admin().await;
+    let response = app.get_admin_audit_events(&[("email", &email)]).await;
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    assert_eq!(body.events[0].kind, AuditEventKind::Signup);
+    assert_eq!(
+        body.events[0].request_id.as_deref(),
+        Some("4bf92f3577b34da6a3ce929d0e0e4736")
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_invalid_incoming_request_id() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/health/live", &app.address))
+        .header("X-Request-Id", "not a valid id")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(request_id).is_ok());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_expose_request_id_to_cross_origin_clients() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/health/live", &app.address))
+        .header("Origin", "http://localhost:8000")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(
+        response.headers()["access-control-expose-headers"],
+        "x-request-id"
+    );
+    app.clean_up().await;
+}
@@ -0,0 +1,89 @@
+use crate::helpers::{TestApp, get_random_email};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+const PHONE_NUMBER: &str = "+14155552671";
+
+// Keeps `POST /phone-number` in flight while Twilio takes `delay` to respond
+async fn signup_and_login_with_slow_sms(app: &TestApp, delay: Duration) {
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(
+        app.post_signup(&signup_body).await.status(),
+        StatusCode::CREATED
+    );
+    let login_body = json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login_body).await.status(), StatusCode::OK);
+
+    Mock::given(path_regex(r"^/2010-04-01/Accounts/.+/Messages\.json$"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201).set_delay(delay))
+        .mount(&app.sms_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_finish_in_flight_requests_during_shutdown() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.sms.timeout = Duration::from_secs(5);
+    })
+    .await;
+    signup_and_login_with_slow_sms(&app, Duration::from_millis(500)).await;
+
+    let body = json!({ "phoneNumber": PHONE_NUMBER });
+    let (response, _) = tokio::join!(app.post_json("/phone-number", &body), async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        app.shutdown();
+    });
+
+    assert_eq!(response.status(), StatusCode::OK);
+    app.wait_for_server().await.unwrap();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_refuse_new_connections_after_shutdown() {
+    let mut app = TestApp::new().await;
+
+    app.shutdown();
+    app.wait_for_server().await.unwrap();
+
+    let result = reqwest::Client::new()
+        .get(format!("{}/", &app.address))
+        .send()
+        .await;
+    assert!(result.is_err());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stop_waiting_for_requests_after_shutdown_timeout() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.application.shutdown_timeout = Duration::from_millis(200);
+        settings.sms.timeout = Duration::from_secs(10);
+    })
+    .await;
+    signup_and_login_with_slow_sms(&app, Duration::from_secs(5)).await;
+
+    let request = app
+        .http_client
+        .clone()
+        .post(format!("{}/phone-number", &app.address))
+        .json(&json!({ "phoneNumber": PHONE_NUMBER }))
+        .send();
+    let started = Instant::now();
+    tokio::select! {
+        _ = request => panic!("Request should still be in flight"),
+        _ = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            app.shutdown();
+            app.wait_for_server().await.unwrap();
+        } => {}
+    }
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    app.clean_up().await;
+}
@@ -144,3 +144,24 @@ async fn should_return_409_if_email_already_exists() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_create_user_once_for_concurrent_signups() {
+    let mut app = TestApp::new().await;
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let responses = tokio::join!(
+        app.post_signup(&body),
+        app.post_signup(&body),
+        app.post_signup(&body)
+    );
+    let mut statuses = [responses.0, responses.1, responses.2].map(|r| r.status().as_u16());
+    statuses.sort();
+
+    assert_eq!(statuses, [201, 409, 409]);
+    app.clean_up().await;
+}
@@ -0,0 +1,81 @@
+use crate::helpers::{TestApp, get_random_email};
+use rcgen::{CertifiedKey, generate_simple_self_signed};
+use reqwest::StatusCode;
+use reqwest::header::LOCATION;
+use serde_json::json;
+use std::path::PathBuf;
+
+// Writes a self-signed certificate for localhost, returning the certificate and key paths
+fn write_certificate() -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("tls-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let CertifiedKey { cert, key_pair } =
+        generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    std::fs::write(&cert_path, cert.pem()).unwrap();
+    std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+    (cert_path, key_path)
+}
+
+async fn tls_app() -> TestApp {
+    let (cert_path, key_path) = write_certificate();
+    TestApp::with_settings(|settings| {
+        settings.application.public_url = "https://auth.example.com".to_owned();
+        settings.tls.enabled = true;
+        settings.tls.cert_path = Some(cert_path.to_string_lossy().into_owned());
+        settings.tls.key_path = Some(key_path.to_string_lossy().into_owned());
+        settings.tls.redirect_http_port = Some(0);
+    })
+    .await
+}
+
+#[tokio::test]
+async fn should_serve_api_over_https() {
+    let mut app = tls_app().await;
+    assert!(app.address.starts_with("https://"));
+
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_http_to_public_https_url() {
+    let mut app = tls_app().await;
+    let redirect_address = app
+        .http_redirect_address
+        .clone()
+        .expect("No HTTP redirect listener");
+
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(format!("{}/csrf-token", redirect_address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()[LOCATION],
+        "https://auth.example.com/csrf-token"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_redirect_without_tls() {
+    let mut app = TestApp::with_settings(|settings| {
+        settings.tls.redirect_http_port = Some(0);
+    })
+    .await;
+
+    assert!(app.address.starts_with("http://"));
+    assert!(app.http_redirect_address.is_none());
+    app.clean_up().await;
+}
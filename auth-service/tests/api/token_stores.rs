@@ -0,0 +1,185 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{
+    BannedTokenStore, Email, HashSetBannedTokenStore, HashmapTwoFACodeStore, LoginAttemptId,
+    PostgresBannedTokenStore, PostgresTwoFACodeStore, TokenStorePurger, TwoFACode, TwoFACodeStore,
+    TwoFACodeStoreError,
+};
+use reqwest::StatusCode;
+use secrecy::SecretString;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+// Every banned token store implementation must pass this
+async fn banned_token_store_suite(store: &(dyn BannedTokenStore + Send + Sync)) {
+    let token = SecretString::from(Uuid::now_v7().to_string());
+    assert_eq!(store.contains_token(&token).await, Ok(false));
+
+    assert!(store.add_token(&token).await.is_ok());
+    assert_eq!(store.contains_token(&token).await, Ok(true));
+
+    // Logging out twice with the same token bans it again
+    assert!(store.add_token(&token).await.is_ok());
+    assert_eq!(store.contains_token(&token).await, Ok(true));
+
+    let other_token = SecretString::from(Uuid::now_v7().to_string());
+    assert_eq!(store.contains_token(&other_token).await, Ok(false));
+}
+
+// Every 2FA code store implementation must pass this
+async fn two_fa_code_store_suite(store: &(dyn TwoFACodeStore + Send + Sync)) {
+    let email = Email::parse(SecretString::from(get_random_email())).unwrap();
+    assert_eq!(
+        store.get_code(&email).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    let (login_attempt_id, code) = (LoginAttemptId::default(), TwoFACode::default());
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+    assert_eq!(store.get_code(&email).await, Ok((login_attempt_id, code)));
+
+    // A new login attempt replaces the pending one
+    let (login_attempt_id, code) = (LoginAttemptId::default(), TwoFACode::default());
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+    assert_eq!(store.get_code(&email).await, Ok((login_attempt_id, code)));
+
+    // A code can only be consumed once, even by concurrent requests
+    let (first, second) = tokio::join!(store.remove_code(&email), store.remove_code(&email));
+    assert!(
+        matches!(
+            (&first, &second),
+            (Ok(()), Err(TwoFACodeStoreError::LoginAttemptIdNotFound))
+                | (Err(TwoFACodeStoreError::LoginAttemptIdNotFound), Ok(()))
+        ),
+        "{first:?} {second:?}"
+    );
+    assert_eq!(
+        store.get_code(&email).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+}
+
+async fn postgres_app() -> TestApp {
+    TestApp::with_settings(|settings| settings.token_store.backend = "postgres".to_owned()).await
+}
+
+#[tokio::test]
+async fn should_pass_banned_token_store_suite_in_memory() {
+    banned_token_store_suite(&HashSetBannedTokenStore::default()).await;
+}
+
+#[tokio::test]
+async fn should_pass_banned_token_store_suite_in_redis() {
+    let mut app = TestApp::new().await;
+    banned_token_store_suite(app.banned_token_store.as_ref()).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_pass_banned_token_store_suite_in_postgres() {
+    let mut app = postgres_app().await;
+    banned_token_store_suite(app.banned_token_store.as_ref()).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_pass_two_fa_code_store_suite_in_memory() {
+    two_fa_code_store_suite(&HashmapTwoFACodeStore::default()).await;
+}
+
+#[tokio::test]
+async fn should_pass_two_fa_code_store_suite_in_redis() {
+    let mut app = TestApp::new().await;
+    two_fa_code_store_suite(app.two_fa_code_store.as_ref()).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_pass_two_fa_code_store_suite_in_postgres() {
+    let mut app = postgres_app().await;
+    two_fa_code_store_suite(app.two_fa_code_store.as_ref()).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_ignore_and_purge_expired_rows_in_postgres() {
+    let mut app = postgres_app().await;
+    let banned_token_store = Arc::new(PostgresBannedTokenStore::new(
+        app.pg_pool.clone(),
+        Duration::ZERO,
+    ));
+    let two_fa_code_store = Arc::new(PostgresTwoFACodeStore::new(
+        app.pg_pool.clone(),
+        Duration::ZERO,
+    ));
+    let purger = TokenStorePurger::new(
+        banned_token_store.clone(),
+        two_fa_code_store.clone(),
+        Duration::from_secs(60),
+    );
+    let token = SecretString::from(Uuid::now_v7().to_string());
+    let email = Email::parse(SecretString::from(get_random_email())).unwrap();
+    banned_token_store.add_token(&token).await.unwrap();
+    two_fa_code_store
+        .add_code(
+            email.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+    // Rows that have not expired yet are kept
+    app.banned_token_store
+        .add_token(&SecretString::from(Uuid::now_v7().to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(banned_token_store.contains_token(&token).await, Ok(false));
+    assert_eq!(
+        two_fa_code_store.get_code(&email).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+    assert_eq!(
+        two_fa_code_store.remove_code(&email).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+    assert_eq!(purger.run_once().await.unwrap(), 2);
+    assert_eq!(purger.run_once().await.unwrap(), 0);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_logged_out_token_without_redis() {
+    let mut app = postgres_app().await;
+    let email = get_random_email();
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(
+        app.post_signup(&signup_body).await.status(),
+        StatusCode::CREATED
+    );
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie returned")
+        .value()
+        .to_owned();
+
+    assert_eq!(app.post_logout().await.status(), StatusCode::OK);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.get_health("ready").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.clean_up().await;
+}
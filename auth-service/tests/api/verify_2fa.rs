@@ -1,7 +1,7 @@
 use crate::helpers::{TestApp, get_random_email};
-use auth_service::Email;
 use auth_service::routes::TwoFactorAuthResponse;
 use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{AccountStatus, Email};
 use axum::http::StatusCode;
 use secrecy::{ExposeSecret, SecretString};
 use serde_json::json;
@@ -159,13 +159,7 @@ async fn should_return_401_if_old_code() {
     let first_login_response = app.post_login(&login_body).await;
     assert_eq!(first_login_response.status(), StatusCode::PARTIAL_CONTENT);
 
-    let first_login_2fa_code = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
-        .await
-        .unwrap();
+    let first_login_2fa_code = app.two_fa_code_store.get_code(&email).await.unwrap();
 
     let second_login_response = app.post_login(&login_body).await;
     assert_eq!(second_login_response.status(), StatusCode::PARTIAL_CONTENT);
@@ -210,13 +204,7 @@ async fn should_return_200_if_correct_code() {
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
 
-    let code_tuple = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
-        .await
-        .unwrap();
+    let code_tuple = app.two_fa_code_store.get_code(&email).await.unwrap();
     let two_fa_auth_response = response.json::<TwoFactorAuthResponse>().await.unwrap();
     let two_fa_payload = json!({
         "email": email.as_ref().expose_secret(),
@@ -265,13 +253,7 @@ async fn should_return_401_if_same_code_twice() {
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
 
-    let code_tuple = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
-        .await
-        .unwrap();
+    let code_tuple = app.two_fa_code_store.get_code(&email).await.unwrap();
     let two_fa_auth_response = response.json::<TwoFactorAuthResponse>().await.unwrap();
     let two_fa_payload = json!({
         "email": email.as_ref().expose_secret(),
@@ -292,3 +274,103 @@ async fn should_return_401_if_same_code_twice() {
     assert_eq!(two_fa_result.status(), StatusCode::UNAUTHORIZED);
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_accept_code_once_for_concurrent_requests() {
+    let mut app = TestApp::new().await;
+    let email = Email::parse(SecretString::from(get_random_email())).unwrap();
+    let signup_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password",
+        "requires2FA": true
+    });
+    assert_eq!(
+        app.post_signup(&signup_payload).await.status(),
+        StatusCode::CREATED
+    );
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    let (login_attempt_id, code) = app.two_fa_code_store.get_code(&email).await.unwrap();
+    let two_fa_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let responses = tokio::join!(
+        app.post_verify_2fa(&two_fa_payload),
+        app.post_verify_2fa(&two_fa_payload),
+        app.post_verify_2fa(&two_fa_payload)
+    );
+    let mut statuses = [responses.0, responses.1, responses.2].map(|r| r.status());
+    statuses.sort();
+
+    assert_eq!(
+        statuses,
+        [
+            StatusCode::OK,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::UNAUTHORIZED
+        ]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_account_disabled_after_login() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let email = Email::parse(SecretString::from(email)).unwrap();
+    let signup_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password",
+        "requires2FA": true
+    });
+    let signup_result = app.post_signup(&signup_payload).await;
+    assert_eq!(signup_result.status(), StatusCode::CREATED);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    app.user_store
+        .set_status(&email, AccountStatus::Disabled)
+        .await
+        .unwrap();
+
+    let code_tuple = app.two_fa_code_store.get_code(&email).await.unwrap();
+    let two_fa_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": code_tuple.0.as_ref().expose_secret(),
+        "2FACode": code_tuple.1.as_ref().expose_secret()
+    });
+
+    let two_fa_result = app.post_verify_2fa(&two_fa_payload).await;
+    assert_eq!(two_fa_result.status(), StatusCode::FORBIDDEN);
+    assert!(
+        two_fa_result
+            .cookies()
+            .all(|cookie| cookie.name() != JWT_COOKIE_NAME)
+    );
+
+    app.clean_up().await;
+}
@@ -1,6 +1,6 @@
 use crate::helpers::{TestApp, get_random_email};
-use auth_service::Email;
 use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::{AccountStatus, Email, ErrorResponse};
 use axum::http::StatusCode;
 use secrecy::{ExposeSecret, SecretString};
 use serde_json::json;
@@ -121,3 +121,45 @@ async fn should_return_401_if_banned_token() {
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_403_if_account_disabled() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let email = Email::parse(SecretString::from(email)).unwrap();
+    let signup_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let signup_response = app.post_signup(&signup_payload).await;
+    assert_eq!(signup_response.status(), StatusCode::CREATED);
+
+    let login_payload = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+    let login_response = app.post_login(&login_payload).await;
+    let jwt_cookie = login_response
+        .cookies()
+        .find(|cookie| cookie.name().eq(JWT_COOKIE_NAME))
+        .unwrap();
+
+    app.user_store
+        .set_status(&email, AccountStatus::Disabled)
+        .await
+        .unwrap();
+
+    let verify_token_payload = json!({
+         "token": jwt_cookie.value(),
+    });
+    let response = app.post_verify_token(&verify_token_payload).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Account disabled"
+    );
+    app.clean_up().await;
+}
@@ -0,0 +1,155 @@
+use crate::helpers::{TestApp, get_random_email};
+use auth_service::ErrorResponse;
+use auth_service::utils::test;
+use reqwest::StatusCode;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn bounce(email: &str, bounce_type: &str, bounced_at: &str) -> serde_json::Value {
+    json!({
+        "RecordType": "Bounce",
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "Description": "The server was unable to deliver your message (ex: unknown user, mailbox not found).",
+        "Email": email,
+        "BouncedAt": bounced_at,
+    })
+}
+
+async fn signup_with_2fa(app: &TestApp, email: &str) {
+    let signup_body = json!({ "email": email, "password": "password123", "requires2FA": true });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn should_return_401_if_webhook_token_is_invalid() {
+    let mut app = TestApp::new().await;
+    let body = bounce(&get_random_email(), "HardBounce", "2025-07-06T09:00:00Z");
+
+    let response = app.post_postmark_webhook(&body, "wrong-token").await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_webhook_payload_is_malformed() {
+    let mut app = TestApp::new().await;
+    let body = json!({ "RecordType": "Bounce", "Email": get_random_email() });
+
+    let response = app
+        .post_postmark_webhook(&body, test::email_client::POSTMARK_WEBHOOK_TOKEN)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_2fa_login_after_hard_bounce() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup_with_2fa(&app, &email).await;
+
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&email.to_uppercase(), "HardBounce", "2025-07-06T09:00:00Z"),
+            test::email_client::POSTMARK_WEBHOOK_TOKEN,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Cannot deliver 2FA code to this email address");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_2fa_login_after_soft_bounce_or_later_delivery() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup_with_2fa(&app, &email).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let login_body = json!({ "email": email, "password": "password123" });
+
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&email, "SoftBounce", "2025-07-06T09:00:00Z"),
+            test::email_client::POSTMARK_WEBHOOK_TOKEN,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    // A spam complaint blocks delivery until the provider reports a newer successful one
+    let spam_complaint = json!({
+        "RecordType": "SpamComplaint",
+        "MessageID": "00000000-0000-0000-0000-000000000000",
+        "Email": email,
+        "BouncedAt": "2025-07-06T10:00:00Z",
+    });
+    let delivery = json!({
+        "RecordType": "Delivery",
+        "MessageID": "00000000-0000-0000-0000-000000000001",
+        "Recipient": email,
+        "DeliveredAt": "2025-07-06T11:00:00-00:00",
+        "Details": "Test delivery webhook details",
+    });
+    for body in [spam_complaint, delivery] {
+        let response = app
+            .post_postmark_webhook(&body, test::email_client::POSTMARK_WEBHOOK_TOKEN)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_2fa_login_after_admin_clears_suppression() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup_with_2fa(&app, &email).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&email, "HardBounce", "2025-07-06T09:00:00Z"),
+            test::email_client::POSTMARK_WEBHOOK_TOKEN,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.login_as_admin().await;
+    let response = app
+        .post_admin_user_action(&email, "clear-email-suppression", &json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    app.clean_up().await;
+}
@@ -1,4 +1,3 @@
-use darling::ToTokens;
 use proc_macro::TokenStream;
 use syn::__private::quote::quote;
 use syn::{ItemFn, parse_macro_input};